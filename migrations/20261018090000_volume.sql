@@ -0,0 +1,37 @@
+CREATE TABLE ge.volume_5m(
+    bucket timestamp NOT NULL,
+    item BIGINT NOT NULL,
+    avg_high_price DECIMAL,
+    high_price_volume BIGINT NOT NULL,
+    avg_low_price DECIMAL,
+    low_price_volume BIGINT NOT NULL,
+    created timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    UNIQUE (item, bucket)
+    )PARTITION BY RANGE (bucket);
+
+SELECT partman.create_parent( p_parent_table => 'ge.volume_5m',
+ p_control => 'bucket',
+ p_interval=> '1 month',
+ p_premake => 2
+ );
+
+CREATE INDEX ON ge.volume_5m(bucket DESC, item);
+
+CREATE TABLE ge.volume_1h(
+    bucket timestamp NOT NULL,
+    item BIGINT NOT NULL,
+    avg_high_price DECIMAL,
+    high_price_volume BIGINT NOT NULL,
+    avg_low_price DECIMAL,
+    low_price_volume BIGINT NOT NULL,
+    created timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    UNIQUE (item, bucket)
+    )PARTITION BY RANGE (bucket);
+
+SELECT partman.create_parent( p_parent_table => 'ge.volume_1h',
+ p_control => 'bucket',
+ p_interval=> '1 month',
+ p_premake => 2
+ );
+
+CREATE INDEX ON ge.volume_1h(bucket DESC, item);
@@ -0,0 +1,24 @@
+CREATE TABLE ge.item(
+    id BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    examine TEXT NOT NULL,
+    members BOOLEAN NOT NULL,
+    lowalch BIGINT,
+    highalch BIGINT,
+    buy_limit BIGINT,
+    value BIGINT NOT NULL,
+    icon TEXT NOT NULL,
+    updated timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+    );
+
+-- One row per changed field each time the wiki mapping differs from ge.item
+CREATE TABLE ge.item_history(
+    created timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    item BIGINT NOT NULL,
+    field TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT
+    );
+
+CREATE INDEX ON ge.item_history(item, created DESC);
+CREATE INDEX ON ge.item_history(created DESC);
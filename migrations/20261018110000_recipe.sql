@@ -0,0 +1,48 @@
+CREATE SCHEMA production;
+
+-- Parsed wiki production recipes. `key` identifies a recipe across refreshes: the output
+-- item, its materials and facilities.
+CREATE TABLE production.recipe(
+    id BIGSERIAL PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    item BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    icon TEXT NOT NULL,
+    ticks TEXT NOT NULL,
+    facilities TEXT,
+    members TEXT NOT NULL,
+    output INT NOT NULL,
+    created timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    checked timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+    );
+
+CREATE TABLE production.recipe_material(
+    recipe BIGINT NOT NULL REFERENCES production.recipe(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    item BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    icon TEXT NOT NULL,
+    count INT NOT NULL,
+    PRIMARY KEY (recipe, position)
+    );
+
+CREATE TABLE production.recipe_skill(
+    recipe BIGINT NOT NULL REFERENCES production.recipe(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    name TEXT NOT NULL,
+    level TEXT NOT NULL,
+    experience TEXT NOT NULL,
+    boostable TEXT NOT NULL,
+    PRIMARY KEY (recipe, position)
+    );
+
+-- Every recipe added, removed or changed by a refresh, with the recipe before and after
+CREATE TABLE production.recipe_log(
+    created timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    key TEXT NOT NULL,
+    change TEXT NOT NULL,
+    old JSONB,
+    new JSONB
+    );
+
+CREATE INDEX ON production.recipe_log(created DESC);
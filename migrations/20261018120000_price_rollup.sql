@@ -0,0 +1,41 @@
+-- Open/high/low/close of the high and low prices stored in ge.price, per item per hour and
+-- per day. Maintained alongside every insert into ge.price.
+CREATE TABLE ge.price_1h(
+    bucket timestamp NOT NULL,
+    item BIGINT NOT NULL,
+    high_open BIGINT,
+    high_high BIGINT,
+    high_low BIGINT,
+    high_close BIGINT,
+    low_open BIGINT,
+    low_high BIGINT,
+    low_low BIGINT,
+    low_close BIGINT,
+    samples INT NOT NULL,
+    PRIMARY KEY (item, bucket)
+    )PARTITION BY RANGE (bucket);
+
+SELECT partman.create_parent( p_parent_table => 'ge.price_1h',
+ p_control => 'bucket',
+ p_interval=> '1 month',
+ p_premake => 2
+ );
+
+CREATE INDEX ON ge.price_1h(bucket DESC);
+
+CREATE TABLE ge.price_1d(
+    bucket timestamp NOT NULL,
+    item BIGINT NOT NULL,
+    high_open BIGINT,
+    high_high BIGINT,
+    high_low BIGINT,
+    high_close BIGINT,
+    low_open BIGINT,
+    low_high BIGINT,
+    low_low BIGINT,
+    low_close BIGINT,
+    samples INT NOT NULL,
+    PRIMARY KEY (item, bucket)
+    );
+
+CREATE INDEX ON ge.price_1d(bucket DESC);
@@ -0,0 +1,9 @@
+-- Progress of the backfill command, one row per item and timestep already imported so an
+-- interrupted run picks up where it stopped.
+CREATE TABLE ge.backfill(
+    item BIGINT NOT NULL,
+    timestep TEXT NOT NULL,
+    points INT NOT NULL,
+    finished timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    PRIMARY KEY (item, timestep)
+    );
@@ -0,0 +1,12 @@
+-- Alert rules checked after every refresh. `triggered` holds while the condition is met so
+-- each crossing is delivered once.
+CREATE TABLE ge.alert(
+    id BIGSERIAL PRIMARY KEY,
+    item BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    threshold BIGINT NOT NULL,
+    window_minutes BIGINT,
+    triggered BOOLEAN NOT NULL DEFAULT false,
+    last_fired timestamp,
+    created timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+    );
@@ -0,0 +1,3 @@
+-- Serves the latest price per item (`distinct on (item) ... order by item, created desc`) and
+-- the price of one item at a point in time without sorting every partition.
+CREATE INDEX ON ge.price(item, created DESC);
@@ -0,0 +1,106 @@
+//! Stand-in for the wiki APIs the tracker depends on. Serves the recorded responses in
+//! `FIXTURES_DIR` (default `./fixtures`) so the tracker can run without network access:
+//!
+//! ```sh
+//! cargo run --bin wiki_fixtures &
+//! OSRS_PRICES_URL=http://localhost:3001/api/v1/osrs OSRS_WIKI_URL=http://localhost:3001 cargo run
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+#[derive(Clone)]
+struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+    async fn serve(&self, name: &str) -> Response {
+        let path = self.dir.join(format!("{}.json", name));
+
+        match tokio::fs::read(&path).await {
+            Ok(e) => ([(header::CONTENT_TYPE, "application/json")], e).into_response(),
+            Err(e) => {
+                println!("Cannot read fixture {:?}: {:?}", path, e);
+                (StatusCode::NOT_FOUND, format!("No fixture named {}", name)).into_response()
+            }
+        }
+    }
+}
+
+async fn mapping(State(fixtures): State<Fixtures>) -> Response {
+    fixtures.serve("mapping").await
+}
+
+async fn latest(State(fixtures): State<Fixtures>) -> Response {
+    fixtures.serve("latest").await
+}
+
+async fn five_minute(State(fixtures): State<Fixtures>) -> Response {
+    fixtures.serve("5m").await
+}
+
+async fn one_hour(State(fixtures): State<Fixtures>) -> Response {
+    fixtures.serve("1h").await
+}
+
+/// Serves `timeseries/<timestep>/<id>.json` when recorded, otherwise the empty series the
+/// wiki returns for items that have never traded.
+async fn timeseries(
+    State(fixtures): State<Fixtures>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let id = query.get("id").cloned().unwrap_or_default();
+    let timestep = query.get("timestep").cloned().unwrap_or_default();
+    let name = format!("timeseries/{}/{}", timestep, id);
+
+    if fixtures.dir.join(format!("{}.json", name)).exists() {
+        return fixtures.serve(&name).await;
+    }
+
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        format!("{{\"data\":[],\"itemId\":{}}}", id),
+    )
+        .into_response()
+}
+
+/// The production query is paginated through an `offset=` path segment. Everything is
+/// recorded in a single page, so later offsets get the empty result the wiki sends once
+/// the query is exhausted.
+async fn special_ask(State(fixtures): State<Fixtures>, Path(path): Path<String>) -> Response {
+    if path.contains("offset=0/") || path.contains("offset%3D0/") {
+        return fixtures.serve("production").await;
+    }
+
+    ([(header::CONTENT_TYPE, "application/json")], "[]").into_response()
+}
+
+#[tokio::main]
+async fn main() {
+    let dir = PathBuf::from(env::var("FIXTURES_DIR").unwrap_or_else(|_| "./fixtures".to_string()));
+    let addr = env::var("FIXTURES_ADDR").unwrap_or_else(|_| "0.0.0.0:3001".to_string());
+
+    let router = Router::new()
+        .route("/api/v1/osrs/mapping", get(mapping))
+        .route("/api/v1/osrs/latest", get(latest))
+        .route("/api/v1/osrs/5m", get(five_minute))
+        .route("/api/v1/osrs/1h", get(one_hour))
+        .route("/api/v1/osrs/timeseries", get(timeseries))
+        .route("/w/*path", get(special_ask))
+        .with_state(Fixtures { dir });
+
+    println!("serving wiki fixtures on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    axum::serve(listener, router).await.unwrap();
+}
@@ -0,0 +1,211 @@
+use crate::repo::data::osrs::{Osrs, TimeseriesTimestep};
+use crate::repo::data::upstream::Upstream;
+use crate::repo::sql::Database;
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+const USAGE: &str =
+    "backfill [--items 561,1201] [--timesteps 5m,1h,24h] [--rate <requests/s>] [--reset]";
+
+/// Attempts per item before it is left for the next run.
+const ATTEMPTS: u32 = 3;
+
+struct Options {
+    items: Option<Vec<i64>>,
+    timesteps: Vec<TimeseriesTimestep>,
+    rate: f64,
+    reset: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            items: None,
+            timesteps: vec![TimeseriesTimestep::OneHour, TimeseriesTimestep::OneDay],
+            rate: 1.0,
+            reset: false,
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--reset" => options.reset = true,
+                "--items" | "--timesteps" | "--rate" => {
+                    let value = match args.next() {
+                        Some(e) => e,
+                        None => return Err(format!("{} needs a value", arg)),
+                    };
+
+                    match arg.as_str() {
+                        "--items" => {
+                            let mut items = Vec::new();
+                            for id in value.split(',') {
+                                match id.trim().parse::<i64>() {
+                                    Ok(e) => items.push(e),
+                                    Err(_) => return Err(format!("bad item id {}", id)),
+                                };
+                            }
+                            options.items = Some(items);
+                        }
+                        "--timesteps" => {
+                            let mut timesteps = Vec::new();
+                            for t in value.split(',') {
+                                match TimeseriesTimestep::parse(t.trim()) {
+                                    Some(e) => timesteps.push(e),
+                                    None => return Err(format!("bad timestep {}", t)),
+                                };
+                            }
+                            options.timesteps = timesteps;
+                        }
+                        _ => {
+                            options.rate = match value.parse::<f64>() {
+                                Ok(e) if e > 0.0 => e,
+                                _ => return Err(format!("bad rate {}", value)),
+                            };
+                        }
+                    };
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            };
+        }
+
+        return Ok(options);
+    }
+}
+
+/// Imports the wiki `/timeseries` history of every known item (or `--items`) into the volume
+/// and rollup tables. Requests are spaced out to `--rate` per second, and each finished item
+/// is recorded in `ge.backfill` so an interrupted run resumes where it stopped; `--reset`
+/// fetches the selected items again. Buckets that are already stored are never overwritten or duplicated.
+pub async fn run(args: &[String], database: &Database) {
+    let options = match Options::parse(args) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("{}\nusage: {}", e, USAGE);
+            return;
+        }
+    };
+
+    let upstream = Upstream::from_env();
+
+    let items = match options.items {
+        Some(e) => e,
+        None => match known_items(&upstream, database).await {
+            Some(e) => e,
+            None => return,
+        },
+    };
+
+    let delay = Duration::from_secs_f64(1.0 / options.rate);
+
+    for timestep in options.timesteps.iter() {
+        if options.reset {
+            match database.reset_backfill(timestep, &items).await {
+                Ok(e) => println!("{}: forgot progress for {} items", timestep.as_str(), e),
+                Err(e) => {
+                    println!("{}: cannot reset progress: {}", timestep.as_str(), e);
+                    return;
+                }
+            };
+        }
+
+        let done: HashSet<i64> = match database.get_backfilled(timestep).await {
+            Ok(e) => e.into_iter().collect(),
+            Err(e) => {
+                println!("{}: cannot read progress: {}", timestep.as_str(), e);
+                return;
+            }
+        };
+
+        let todo: Vec<&i64> = items.iter().filter(|e| !done.contains(e)).collect();
+
+        println!(
+            "{}: {} items to fetch, {} already done",
+            timestep.as_str(),
+            todo.len(),
+            items.len() - todo.len()
+        );
+
+        let mut stored: u64 = 0;
+        let mut failed = 0;
+
+        for (i, id) in todo.iter().enumerate() {
+            match backfill_item(&upstream, database, id, timestep, delay).await {
+                Some(e) => stored += e,
+                None => failed += 1,
+            };
+
+            if (i + 1) % 100 == 0 {
+                println!("{}: {} of {} items", timestep.as_str(), i + 1, todo.len());
+            }
+        }
+
+        println!(
+            "{}: stored {} new rows, {} items failed and will be retried on the next run",
+            timestep.as_str(),
+            stored,
+            failed
+        );
+    }
+}
+
+/// Fetches and stores one item, backing off after each failed attempt. Returns the rows
+/// written, or None if every attempt failed.
+async fn backfill_item(
+    upstream: &Upstream,
+    database: &Database,
+    id: &i64,
+    timestep: &TimeseriesTimestep,
+    delay: Duration,
+) -> Option<u64> {
+    for attempt in 1..=ATTEMPTS {
+        tokio::time::sleep(delay * attempt).await;
+
+        let points = match Osrs::fetch_timeseries(upstream, id, timestep).await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("{} {}: attempt {}: {}", timestep.as_str(), id, attempt, e);
+                continue;
+            }
+        };
+
+        match database.insert_timeseries(id, timestep, &points).await {
+            Ok(e) => return Some(e),
+            Err(e) => {
+                println!("{} {}: attempt {}: {}", timestep.as_str(), id, attempt, e);
+                continue;
+            }
+        };
+    }
+
+    return None;
+}
+
+/// Item ids from `ge.item`, fetching and storing the mapping first if it is empty.
+async fn known_items(upstream: &Upstream, database: &Database) -> Option<Vec<i64>> {
+    match database.get_items().await {
+        Ok(e) if !e.is_empty() => return Some(e.iter().map(|m| m.id).collect()),
+        Ok(_) => (),
+        Err(e) => {
+            println!("cannot read items: {}", e);
+            return None;
+        }
+    };
+
+    let maps = match Osrs::fetch_maps(upstream).await {
+        Ok(e) => e,
+        Err(e) => {
+            println!("no stored items and cannot fetch the mapping: {}", e);
+            return None;
+        }
+    };
+
+    match database.upsert_items(&maps).await {
+        Ok(_) => (),
+        Err(e) => println!("cannot store mapping: {}", e),
+    };
+
+    return Some(maps.iter().map(|m| m.id).collect());
+}
@@ -0,0 +1,27 @@
+use crate::repo::sql::Database;
+
+/// Removes `ge.price` rows that repeat the previous snapshot of the same item, one partition
+/// at a time so each delete only locks a single month.
+pub async fn run(database: &Database) {
+    let partitions = match database.get_partitions("ge.price").await {
+        Ok(e) => e,
+        Err(e) => {
+            println!("cannot list partitions: {}", e);
+            return;
+        }
+    };
+
+    let mut total: u64 = 0;
+
+    for partition in partitions {
+        match database.dedupe_ge_price_partition(&partition).await {
+            Ok(e) => {
+                println!("{}: deleted {} duplicate rows", partition, e);
+                total += e;
+            }
+            Err(e) => println!("{}: {}", partition, e),
+        };
+    }
+
+    println!("deleted {} duplicate rows in total", total);
+}
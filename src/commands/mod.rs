@@ -0,0 +1,20 @@
+//! One-off maintenance commands, run as `osrs-ge-tracker <command>` instead of the server.
+
+pub mod backfill;
+pub mod dedupe;
+pub mod rollup;
+
+use crate::repo::sql::Database;
+
+/// Runs the named command with the arguments that follow it. Returns false if there is no
+/// command with that name.
+pub async fn run(name: &str, args: &[String], database: &Database) -> bool {
+    match name {
+        "backfill" => backfill::run(args, database).await,
+        "dedupe-prices" => dedupe::run(database).await,
+        "rollup-prices" => rollup::run(database).await,
+        _ => return false,
+    };
+
+    true
+}
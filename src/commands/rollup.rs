@@ -0,0 +1,23 @@
+use crate::repo::sql::Database;
+
+/// Rebuilds the hourly and daily price rollups from everything stored in `ge.price`, for
+/// history recorded before the rollups existed. Safe to run again at any time.
+pub async fn run(database: &Database) {
+    let partitions = match database.get_partitions("ge.price").await {
+        Ok(e) => e,
+        Err(e) => {
+            println!("cannot list partitions: {}", e);
+            return;
+        }
+    };
+
+    for partition in partitions {
+        match database.rebuild_ge_price_rollups(&partition).await {
+            Ok((hours, days)) => println!(
+                "{}: wrote {} hourly and {} daily buckets",
+                partition, hours, days
+            ),
+            Err(e) => println!("{}: {}", partition, e),
+        };
+    }
+}
@@ -1,13 +1,19 @@
+mod commands;
 mod repo;
 mod routes;
 
+use crate::repo::data::alert::Webhook;
+use crate::repo::data::freshness::Freshness;
 use crate::repo::data::osrs::Osrs;
+use crate::repo::data::tax::GeTax;
+use crate::repo::data::upstream::Upstream;
+use crate::repo::retention::Retention;
 use crate::repo::sql::Database;
 
 use std::env;
 
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use dotenvy::dotenv;
@@ -22,9 +28,22 @@ async fn main() {
     if env::var("DATABASE_URL").is_err() {
         panic!("DATABASE_URL not in environment vars");
     }
-    let database = Database::new(env::var("DATABASE_URL").unwrap())
-        .await
-        .unwrap();
+    let database = match Database::new(env::var("DATABASE_URL").unwrap()).await {
+        Ok(e) => e,
+        Err(e) => panic!("cannot start without a database: {}", e),
+    };
+
+    // `osrs-ge-tracker <command>` runs a maintenance command instead of the server
+    if let Some(command) = env::args().nth(1) {
+        let args: Vec<String> = env::args().skip(2).collect();
+        if !commands::run(&command, &args, &database).await {
+            panic!(
+                "unknown command {}, expected one of: backfill, dedupe-prices, rollup-prices",
+                command
+            );
+        }
+        return;
+    }
 
     tracing_subscriber::registry()
         .with(
@@ -34,7 +53,16 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let osrs = Osrs::new(database.clone()).await.unwrap();
+    tokio::spawn(Retention::from_env().schedule(database.clone()));
+
+    let osrs = Osrs::new(
+        database.clone(),
+        GeTax::from_env(),
+        Upstream::from_env(),
+        Freshness::from_env(),
+        Webhook::from_env(),
+    )
+    .await;
 
     let state = AppState { database, osrs };
 
@@ -46,6 +74,49 @@ async fn main() {
         .route("/highalch", get(routes::highalch::get))
         .route("/lowalch", get(routes::lowalch::get))
         .route("/crafting", get(routes::crafting::get))
+        .route("/crafting/chains", get(routes::chains::get))
+        .route("/crafting/chains/:id", get(routes::chains::get_one))
+        .route("/flipping", get(routes::flipping::get))
+        .route("/spells", get(routes::spells::get))
+        .route("/items/:id", get(routes::items::get))
+        .route("/events", get(routes::events::get))
+        .route(
+            "/profile",
+            get(routes::profile::get).post(routes::profile::save),
+        )
+        .route(
+            "/alerts",
+            get(routes::alerts::get).post(routes::alerts::create),
+        )
+        .route("/alerts/:id/delete", post(routes::alerts::delete))
+        .route("/api/v1/highalch", get(routes::api::v1::highalch))
+        .route("/api/v1/lowalch", get(routes::api::v1::lowalch))
+        .route("/api/v1/crafting", get(routes::api::v1::crafting))
+        .route("/api/v1/flipping", get(routes::api::v1::flipping))
+        .route("/api/v1/spells", get(routes::api::v1::spells))
+        .route("/api/v1/crafting/chains", get(routes::api::v1::chains))
+        .route("/api/v1/crafting/chains/:id", get(routes::api::v1::chain))
+        .route(
+            "/api/v1/crafting/changes",
+            get(routes::api::v1::recipe_changes),
+        )
+        .route("/api/v1/status", get(routes::api::v1::status))
+        .route(
+            "/api/v1/alerts",
+            get(routes::api::v1::alerts).post(routes::api::v1::create_alert),
+        )
+        .route("/api/v1/alerts/:id", delete(routes::api::v1::delete_alert))
+        .route("/api/v1/prices", get(routes::api::v1::prices))
+        .route("/api/v1/prices/:id", get(routes::api::v1::price))
+        .route("/api/v1/volumes/:timestep", get(routes::api::v1::volumes))
+        .route(
+            "/api/v1/volumes/:timestep/:id",
+            get(routes::api::v1::volume),
+        )
+        .route("/api/v1/mapping", get(routes::api::v1::mapping))
+        .route("/api/v1/mapping/:id", get(routes::api::v1::mapping_one))
+        .route("/api/v1/items/:id/history", get(routes::api::v1::history))
+        .route("/api/v1/items/:id/changes", get(routes::api::v1::changes))
         .nest_service(
             "/public",
             ServeDir::new(format!("{}/public", assets_path.to_str().unwrap())),
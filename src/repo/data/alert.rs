@@ -0,0 +1,317 @@
+use crate::repo::data::error::OsrsError;
+
+use std::env;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use validator::{Validate, ValidationError};
+
+/// Basis points in one percent. `PercentChange` values are worked out in basis points so
+/// that fractional changes still compare correctly against a whole-percent threshold.
+pub const BASIS_POINTS_PER_PERCENT: i64 = 100;
+
+/// Largest `PercentChange` threshold accepted, a hundredfold move.
+pub const MAX_PERCENT_THRESHOLD: i64 = 10_000;
+
+/// What an alert rule watches. Thresholds are in gp, except `PercentChange` which is a
+/// percentage of the high price over `window_minutes` in either direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    HighAbove,
+    HighBelow,
+    LowAbove,
+    LowBelow,
+    MarginAbove,
+    AlchProfitAbove,
+    PercentChange,
+}
+
+pub const ALERT_KINDS: [AlertKind; 7] = [
+    AlertKind::HighAbove,
+    AlertKind::HighBelow,
+    AlertKind::LowAbove,
+    AlertKind::LowBelow,
+    AlertKind::MarginAbove,
+    AlertKind::AlchProfitAbove,
+    AlertKind::PercentChange,
+];
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::HighAbove => "high_above",
+            AlertKind::HighBelow => "high_below",
+            AlertKind::LowAbove => "low_above",
+            AlertKind::LowBelow => "low_below",
+            AlertKind::MarginAbove => "margin_above",
+            AlertKind::AlchProfitAbove => "alch_profit_above",
+            AlertKind::PercentChange => "percent_change",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        ALERT_KINDS.iter().find(|d| d.as_str() == s).copied()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlertKind::HighAbove => "High price above",
+            AlertKind::HighBelow => "High price below",
+            AlertKind::LowAbove => "Low price above",
+            AlertKind::LowBelow => "Low price below",
+            AlertKind::MarginAbove => "Margin after tax above",
+            AlertKind::AlchProfitAbove => "High alch profit above",
+            AlertKind::PercentChange => "High price changes by",
+        }
+    }
+
+    /// Whether `value`, as produced for this kind, satisfies the rule. `PercentChange`
+    /// values are in basis points while their threshold is in percent.
+    pub fn is_met(&self, value: i64, threshold: i64) -> bool {
+        match self {
+            AlertKind::HighBelow | AlertKind::LowBelow => value < threshold,
+            AlertKind::PercentChange => {
+                value.abs() >= threshold.saturating_mul(BASIS_POINTS_PER_PERCENT)
+            }
+            _ => value > threshold,
+        }
+    }
+}
+
+/// A stored alert rule. `triggered` is set when the rule fires and cleared once the
+/// condition stops holding, so each crossing is only delivered once.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRule {
+    pub id: i64,
+    pub item: i64,
+    pub kind: AlertKind,
+    pub threshold: i64,
+    pub window_minutes: Option<i64>,
+    pub triggered: bool,
+    pub last_fired: Option<NaiveDateTime>,
+    pub created: NaiveDateTime,
+}
+
+impl AlertRule {
+    pub fn describe(&self) -> String {
+        match self.kind {
+            AlertKind::PercentChange => format!(
+                "{} {}% in {} minutes",
+                self.kind.label(),
+                self.threshold,
+                self.window_minutes.unwrap_or_default()
+            ),
+            _ => format!("{} {} gp", self.kind.label(), self.threshold),
+        }
+    }
+}
+
+/// Alert rule as submitted through the form or the API.
+#[derive(Deserialize, Validate, Clone, Debug)]
+#[validate(schema(function = "validate_window"))]
+pub struct NewAlertRule {
+    pub item: i64,
+    pub kind: AlertKind,
+    pub threshold: i64,
+    #[serde(default, deserialize_with = "number_or_empty")]
+    #[validate(range(min = 5, max = 10080))]
+    pub window_minutes: Option<i64>,
+}
+
+/// Accepts a JSON number as well as the string an HTML form sends, treating an empty
+/// input as not set.
+fn number_or_empty<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(i64),
+        Text(String),
+    }
+
+    match Option::<Raw>::deserialize(deserializer)? {
+        Some(Raw::Number(e)) => Ok(Some(e)),
+        Some(Raw::Text(e)) if !e.trim().is_empty() => match e.trim().parse() {
+            Ok(v) => Ok(Some(v)),
+            Err(err) => Err(serde::de::Error::custom(err)),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn validate_window(rule: &NewAlertRule) -> Result<(), ValidationError> {
+    match (rule.kind, rule.window_minutes) {
+        (AlertKind::PercentChange, None) => Err(ValidationError::new("window_minutes_required")),
+        (AlertKind::PercentChange, Some(_))
+            if !(1..=MAX_PERCENT_THRESHOLD).contains(&rule.threshold) =>
+        {
+            Err(ValidationError::new("percent_out_of_range"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// How long a delivery may take before it is given up on. Alerts are sent from the refresh
+/// loop, so a webhook that hangs must not hold up the next refresh.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where fired alerts are posted. The payload is Discord's webhook format, which most chat
+/// tools accept as well. Without a URL alerts are only logged.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: Option<String>,
+    pub username: String,
+    client: reqwest::Client,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Webhook {
+            url: None,
+            username: "GE Tracker".to_string(),
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Webhook {
+    /// Reads `ALERT_WEBHOOK_URL` and `ALERT_WEBHOOK_USERNAME`.
+    pub fn from_env() -> Self {
+        let default = Webhook::default();
+
+        Webhook {
+            url: match env::var("ALERT_WEBHOOK_URL") {
+                Ok(e) if !e.trim().is_empty() => Some(e.trim().to_string()),
+                _ => default.url,
+            },
+            username: env::var("ALERT_WEBHOOK_USERNAME").unwrap_or(default.username),
+            client: default.client,
+        }
+    }
+
+    pub async fn send(&self, rule: &AlertRule, name: &str, value: i64) -> Result<(), OsrsError> {
+        let current = match rule.kind {
+            AlertKind::PercentChange => {
+                format!("{:+.2}%", value as f64 / BASIS_POINTS_PER_PERCENT as f64)
+            }
+            _ => format!("{} gp", value),
+        };
+        let message = format!("{}: {} (now {})", name, rule.describe(), current);
+
+        let url = match &self.url {
+            Some(e) => e,
+            None => {
+                println!("alert {} fired: {}", rule.id, message);
+                return Ok(());
+            }
+        };
+
+        let payload = json!({
+            "username": self.username,
+            "content": message,
+            "embeds": [{
+                "title": name,
+                "description": rule.describe(),
+                "fields": [
+                    { "name": "Now", "value": current, "inline": true },
+                    { "name": "Item", "value": rule.item.to_string(), "inline": true },
+                ],
+            }],
+        });
+
+        let res = match self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(payload.to_string())
+            .send()
+            .await
+        {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error delivering alert {}: {:?}", rule.id, e);
+                return Err(OsrsError::Network(format!(
+                    "couldn't deliver alert {}",
+                    rule.id
+                )));
+            }
+        };
+
+        if !res.status().is_success() {
+            return Err(OsrsError::Network(format!(
+                "delivering alert {} returned {}",
+                rule.id,
+                res.status()
+            )));
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_met_compares_in_the_kinds_direction() {
+        assert!(AlertKind::HighAbove.is_met(101, 100));
+        assert!(!AlertKind::HighAbove.is_met(100, 100));
+        assert!(AlertKind::LowBelow.is_met(99, 100));
+        assert!(!AlertKind::LowBelow.is_met(100, 100));
+        assert!(AlertKind::MarginAbove.is_met(-5, -10));
+    }
+
+    #[test]
+    fn is_met_percent_change_fires_either_way() {
+        // Values are in basis points, thresholds in percent
+        assert!(AlertKind::PercentChange.is_met(500, 5));
+        assert!(AlertKind::PercentChange.is_met(-700, 5));
+        assert!(!AlertKind::PercentChange.is_met(-499, 5));
+        assert!(!AlertKind::PercentChange.is_met(1_000_000, i64::MAX));
+    }
+
+    fn window(json: &str) -> Result<Option<i64>, serde_json::Error> {
+        let body = format!(
+            r#"{{"item": 2, "kind": "percent_change", "threshold": 5{}}}"#,
+            json
+        );
+        serde_json::from_str::<NewAlertRule>(&body).map(|e| e.window_minutes)
+    }
+
+    #[test]
+    fn number_or_empty_accepts_numbers_and_form_strings() {
+        assert_eq!(window(r#", "window_minutes": 30"#).unwrap(), Some(30));
+        assert_eq!(window(r#", "window_minutes": " 45 ""#).unwrap(), Some(45));
+        assert_eq!(window(r#", "window_minutes": """#).unwrap(), None);
+        assert_eq!(window(r#", "window_minutes": null"#).unwrap(), None);
+        assert_eq!(window("").unwrap(), None);
+        assert!(window(r#", "window_minutes": "soon""#).is_err());
+    }
+
+    #[test]
+    fn percent_change_threshold_is_bounded() {
+        let rule = |threshold: i64| NewAlertRule {
+            item: 2,
+            kind: AlertKind::PercentChange,
+            threshold,
+            window_minutes: Some(60),
+        };
+
+        assert!(rule(1).validate().is_ok());
+        assert!(rule(MAX_PERCENT_THRESHOLD).validate().is_ok());
+        assert!(rule(0).validate().is_err());
+        assert!(rule(MAX_PERCENT_THRESHOLD + 1).validate().is_err());
+        assert!(rule(i64::MAX).validate().is_err());
+    }
+}
@@ -0,0 +1,566 @@
+use crate::repo::data::osrs::{CraftingItem, GePrice, GeVolume, OsrsMap};
+use crate::repo::data::pricing::PriceBasis;
+use crate::repo::data::tax::GeTax;
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// One item in a crafting chain: either bought from the GE or crafted from `materials`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainNode {
+    pub id: i64,
+    pub name: String,
+    pub icon: String,
+    /// Units needed by the step above, fractional when a recipe makes several at once.
+    pub quantity: f64,
+    /// Cost of `quantity` units, bought or crafted.
+    pub cost: f64,
+    pub crafted: bool,
+    /// Times the recipe is made to get `quantity`, zero when bought.
+    pub crafts: f64,
+    pub facilities: Option<String>,
+    pub xp: Vec<SkillXp>,
+    pub limit: Option<i64>,
+    pub last_trade: Option<i64>,
+    pub materials: Vec<ChainNode>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillXp {
+    pub skill: String,
+    pub xp: f64,
+}
+
+/// A chain node flattened for display, `depth` levels below the product.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainStep {
+    pub depth: usize,
+    pub node: ChainNode,
+}
+
+impl ChainNode {
+    /// Depth-first, the product first and every material below the step that needs it.
+    pub fn steps(&self) -> Vec<ChainStep> {
+        let mut steps = Vec::new();
+        self.push_steps(0, &mut steps);
+        return steps;
+    }
+
+    fn push_steps(&self, depth: usize, steps: &mut Vec<ChainStep>) {
+        let mut node = self.clone();
+        node.materials = Vec::new();
+        steps.push(ChainStep { depth, node });
+
+        for m in &self.materials {
+            m.push_steps(depth + 1, steps);
+        }
+    }
+
+    /// XP from every crafted step, summed per skill in the order the skills first appear.
+    fn total_xp(&self) -> Vec<SkillXp> {
+        let mut total: Vec<SkillXp> = Vec::new();
+
+        for step in self.steps() {
+            for x in step.node.xp {
+                match total.iter_mut().find(|d| d.skill == x.skill) {
+                    Some(e) => e.xp += x.xp,
+                    None => total.push(x),
+                }
+            }
+        }
+
+        return total;
+    }
+
+    /// Bought materials, which are the only ones that use up buy limits and carry prices.
+    fn bought(&self) -> Vec<&ChainNode> {
+        if !self.crafted {
+            return vec![self];
+        }
+
+        return self.materials.iter().flat_map(|m| m.bought()).collect();
+    }
+}
+
+/// The cheapest way to make one craft of an item, compared with the GE price it sells at.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainProfit {
+    pub name: String,
+    pub icon: String,
+    pub id: i64,
+    pub members: bool,
+    pub output: u16,
+    pub price: i64,
+    /// Cost of buying the direct materials, as on the crafting table.
+    pub direct_cost: Option<i64>,
+    /// Cost when every material is bought or crafted, whichever is cheaper.
+    pub chain_cost: i64,
+    pub tax: i64,
+    pub profit_after_tax: i64,
+    pub profit_margin_after_tax: f64,
+    /// Crafted steps in the chain, including the product itself.
+    pub steps: usize,
+    pub xp: Vec<SkillXp>,
+    pub total_xp: f64,
+    pub crafts_per_limit: Option<i64>,
+    pub limit_profit: Option<i64>,
+    pub volume: Option<i64>,
+    pub last_trade: Option<i64>,
+    pub tree: ChainNode,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Best {
+    unit_cost: f64,
+    /// Index into the item's recipes, or None when buying is cheapest.
+    recipe: Option<usize>,
+}
+
+/// Works out the cheapest way to obtain items from the recipe graph: buy at the GE `high`
+/// price, or craft through any recipe whose materials can be obtained, recursively. An item
+/// that is already being crafted further up the chain can only be bought, which breaks
+/// cycles such as items that can be both made from and turned back into each other.
+pub struct ChainResolver<'a> {
+    recipes: HashMap<i64, Vec<&'a CraftingItem>>,
+    /// Names, icons and buy limits of the items that are bought.
+    map: &'a HashMap<i64, OsrsMap>,
+    ge: &'a HashMap<i64, GePrice>,
+    /// Only holds results that did not depend on a cycle being cut, as those can differ
+    /// depending on where the item was reached from.
+    memo: HashMap<i64, Option<Best>>,
+}
+
+impl<'a> ChainResolver<'a> {
+    pub fn new(
+        crafting: &'a [CraftingItem],
+        map: &'a HashMap<i64, OsrsMap>,
+        ge: &'a HashMap<i64, GePrice>,
+    ) -> Self {
+        let mut recipes: HashMap<i64, Vec<&'a CraftingItem>> = HashMap::new();
+
+        // Recipes without materials would make their output free
+        for c in crafting
+            .iter()
+            .filter(|d| !d.materials.is_empty() && d.output > 0)
+        {
+            recipes.entry(c.id).or_default().push(c);
+        }
+
+        ChainResolver {
+            recipes,
+            map,
+            ge,
+            memo: HashMap::new(),
+        }
+    }
+
+    /// Chains for every craftable item with a GE price, most profitable first.
+    pub fn resolve_all(
+        &mut self,
+        volume: &HashMap<i64, GeVolume>,
+        tax: &GeTax,
+    ) -> Vec<ChainProfit> {
+        let mut ids: Vec<i64> = self.recipes.keys().copied().collect();
+        ids.sort();
+
+        let mut res: Vec<ChainProfit> = ids
+            .into_iter()
+            .filter_map(|id| self.resolve(&id, volume, tax))
+            .collect();
+
+        res.sort_by_key(|d| d.profit_after_tax);
+        res.reverse();
+
+        return res;
+    }
+
+    pub fn resolve(
+        &mut self,
+        id: &i64,
+        volume: &HashMap<i64, GeVolume>,
+        tax: &GeTax,
+    ) -> Option<ChainProfit> {
+        let price = self.ge.get(id)?.high?;
+
+        // The product itself is always crafted, through its cheapest recipe
+        let mut path = vec![*id];
+        let mut cheapest: Option<(f64, usize)> = None;
+        for i in 0..self.recipes.get(id)?.len() {
+            let recipe = self.recipes[id][i];
+            if let Some(cost) = self.recipe_cost(recipe, &mut path).0 {
+                if cheapest.is_none_or(|(c, _)| cost < c) {
+                    cheapest = Some((cost, i));
+                }
+            }
+        }
+        let recipe = self.recipes[id][cheapest?.1];
+
+        let tree = self.expand(
+            id,
+            recipe.output as f64,
+            Some(Best {
+                unit_cost: cheapest?.0 / recipe.output as f64,
+                recipe: Some(cheapest?.1),
+            }),
+            &mut Vec::new(),
+        )?;
+
+        let direct_cost = recipe
+            .materials
+            .iter()
+            .map(|m| {
+                self.ge
+                    .get(&m.id)
+                    .and_then(|p| p.high)
+                    .map(|p| p * m.count as i64)
+            })
+            .sum::<Option<i64>>();
+
+        let chain_cost = tree.cost.round() as i64;
+        let output = recipe.output as i64;
+        let item_tax = tax.tax(id, price) * output;
+        let profit_after_tax = tax.after_tax(id, price) * output - chain_cost;
+
+        let bought = tree.bought();
+
+        let crafts_per_limit = bought
+            .iter()
+            .filter_map(|m| m.limit.map(|l| (l as f64 / m.quantity).floor() as i64))
+            .min();
+
+        // A chain is only as fresh as the oldest price it is built from
+        let mut last_trade = self.ge.get(id).and_then(|p| PriceBasis::High.last_trade(p));
+        for m in &bought {
+            last_trade = match (last_trade, m.last_trade) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                _ => None,
+            };
+        }
+
+        let xp = tree.total_xp();
+
+        Some(ChainProfit {
+            name: recipe.name.clone(),
+            icon: recipe.icon.clone(),
+            id: *id,
+            members: recipe.members == "Yes",
+            output: recipe.output,
+            price,
+            direct_cost,
+            chain_cost,
+            tax: item_tax,
+            profit_after_tax,
+            profit_margin_after_tax: profit_after_tax as f64 * 100_f64
+                / (price as f64 * output as f64),
+            steps: tree.steps().iter().filter(|d| d.node.crafted).count(),
+            total_xp: xp.iter().map(|d| d.xp).sum(),
+            xp,
+            crafts_per_limit,
+            limit_profit: crafts_per_limit.map(|l| l * profit_after_tax),
+            volume: volume.get(id).map(|v| v.total()),
+            last_trade,
+            tree,
+        })
+    }
+
+    /// Cheapest way to get one unit of `id`, and whether a cycle had to be cut to find it.
+    fn best(&mut self, id: &i64, path: &mut Vec<i64>) -> (Option<Best>, bool) {
+        if let Some(e) = self.memo.get(id) {
+            return (*e, false);
+        }
+
+        let buy = self.ge.get(id).and_then(|p| p.high).map(|p| Best {
+            unit_cost: p as f64,
+            recipe: None,
+        });
+
+        if path.contains(id) {
+            return (buy, true);
+        }
+
+        let recipes = match self.recipes.get(id) {
+            Some(e) => e.clone(),
+            None => {
+                self.memo.insert(*id, buy);
+                return (buy, false);
+            }
+        };
+
+        path.push(*id);
+
+        let mut best = buy;
+        let mut cut = false;
+
+        for (i, recipe) in recipes.iter().enumerate() {
+            let (cost, c) = self.recipe_cost(recipe, path);
+            cut |= c;
+
+            if let Some(cost) = cost {
+                let unit_cost = cost / recipe.output as f64;
+                if best.is_none_or(|b| unit_cost < b.unit_cost) {
+                    best = Some(Best {
+                        unit_cost,
+                        recipe: Some(i),
+                    });
+                }
+            }
+        }
+
+        path.pop();
+
+        if !cut {
+            self.memo.insert(*id, best);
+        }
+
+        return (best, cut);
+    }
+
+    /// Cost of one craft of `recipe` with every material obtained the cheapest way.
+    fn recipe_cost(&mut self, recipe: &CraftingItem, path: &mut Vec<i64>) -> (Option<f64>, bool) {
+        let mut total = 0_f64;
+        let mut cut = false;
+
+        for m in &recipe.materials {
+            let (best, c) = self.best(&m.id, path);
+            cut |= c;
+
+            match best {
+                Some(b) => total += b.unit_cost * m.count as f64,
+                None => return (None, cut),
+            };
+        }
+
+        return (Some(total), cut);
+    }
+
+    /// Builds the node for `quantity` units of `id` obtained as `choice`, recursing into the
+    /// materials of crafted items.
+    fn expand(
+        &mut self,
+        id: &i64,
+        quantity: f64,
+        choice: Option<Best>,
+        path: &mut Vec<i64>,
+    ) -> Option<ChainNode> {
+        let choice = choice?;
+        let price = self.ge.get(id);
+
+        let recipe_index = match choice.recipe {
+            Some(e) => e,
+            None => {
+                let item = self.map.get(id);
+
+                return Some(ChainNode {
+                    id: *id,
+                    name: item.map(|m| m.name.clone()).unwrap_or_default(),
+                    icon: item.map(|m| m.icon.clone()).unwrap_or_default(),
+                    quantity,
+                    cost: choice.unit_cost * quantity,
+                    crafted: false,
+                    crafts: 0_f64,
+                    facilities: None,
+                    xp: Vec::new(),
+                    limit: item.and_then(|m| m.limit),
+                    last_trade: price.and_then(|p| PriceBasis::High.last_trade(p)),
+                    materials: Vec::new(),
+                });
+            }
+        };
+
+        let recipe = self.recipes[id][recipe_index];
+        let crafts = quantity / recipe.output as f64;
+
+        path.push(*id);
+
+        let mut materials = Vec::new();
+        for m in &recipe.materials {
+            // Items already being crafted further up can only be bought, as in `best`
+            let choice = match path.contains(&m.id) {
+                true => self.ge.get(&m.id).and_then(|p| p.high).map(|p| Best {
+                    unit_cost: p as f64,
+                    recipe: None,
+                }),
+                false => self.best(&m.id, path).0,
+            };
+
+            materials.push(self.expand(&m.id, m.count as f64 * crafts, choice, path)?);
+        }
+
+        path.pop();
+
+        Some(ChainNode {
+            id: *id,
+            name: recipe.name.clone(),
+            icon: recipe.icon.clone(),
+            quantity,
+            cost: materials.iter().map(|m| m.cost).sum(),
+            crafted: true,
+            crafts,
+            facilities: recipe.facilities.clone(),
+            xp: recipe
+                .skills
+                .iter()
+                .map(|s| SkillXp {
+                    skill: s.name.clone(),
+                    xp: s.xp() * crafts,
+                })
+                .collect(),
+            limit: None,
+            last_trade: price.and_then(|p| PriceBasis::High.last_trade(p)),
+            materials,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::data::osrs::CraftingMaterial;
+
+    fn recipe(id: i64, output: u16, materials: &[(i64, u8)]) -> CraftingItem {
+        CraftingItem {
+            name: format!("Item {}", id),
+            id,
+            members: "Yes".to_string(),
+            output,
+            materials: materials
+                .iter()
+                .map(|(id, count)| CraftingMaterial {
+                    name: format!("Item {}", id),
+                    id: *id,
+                    count: *count,
+                    ..CraftingMaterial::default()
+                })
+                .collect(),
+            ..CraftingItem::default()
+        }
+    }
+
+    fn prices(high: &[(i64, i64)]) -> HashMap<i64, GePrice> {
+        high.iter()
+            .map(|(id, p)| {
+                (
+                    *id,
+                    GePrice {
+                        high: Some(*p),
+                        ..GePrice::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn maps(ids: &[i64]) -> HashMap<i64, OsrsMap> {
+        ids.iter()
+            .map(|id| {
+                (
+                    *id,
+                    OsrsMap {
+                        examine: String::new(),
+                        id: *id,
+                        members: true,
+                        lowalch: None,
+                        limit: Some(3000),
+                        value: 0,
+                        highalch: None,
+                        icon: format!("Item {}.png", id),
+                        name: format!("Item {}", id),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn resolve(
+        crafting: &[CraftingItem],
+        map: &HashMap<i64, OsrsMap>,
+        ge: &HashMap<i64, GePrice>,
+        id: i64,
+    ) -> ChainProfit {
+        ChainResolver::new(crafting, map, ge)
+            .resolve(&id, &HashMap::new(), &GeTax::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn crafts_materials_when_cheaper_than_buying() {
+        // 1 is made from two of 2, and 2 can be bought or made from one of 3
+        let crafting = vec![recipe(1, 1, &[(2, 2)]), recipe(2, 1, &[(3, 1)])];
+        let map = maps(&[1, 2, 3]);
+
+        let ge = prices(&[(1, 1_000), (2, 100), (3, 30)]);
+        let chain = resolve(&crafting, &map, &ge, 1);
+
+        assert_eq!(chain.chain_cost, 60);
+        assert_eq!(chain.direct_cost, Some(200));
+        assert_eq!(chain.steps, 2);
+        let material = &chain.tree.materials[0];
+        assert!(material.crafted);
+        assert_eq!(material.materials[0].id, 3);
+        assert_eq!(material.materials[0].name, "Item 3");
+        assert_eq!(material.materials[0].limit, Some(3000));
+    }
+
+    #[test]
+    fn buys_materials_when_cheaper_than_crafting() {
+        let crafting = vec![recipe(1, 1, &[(2, 2)]), recipe(2, 1, &[(3, 1)])];
+        let map = maps(&[1, 2, 3]);
+
+        let ge = prices(&[(1, 1_000), (2, 100), (3, 150)]);
+        let chain = resolve(&crafting, &map, &ge, 1);
+
+        assert_eq!(chain.chain_cost, 200);
+        assert_eq!(chain.steps, 1);
+        let material = &chain.tree.materials[0];
+        assert!(!material.crafted);
+        assert_eq!(material.quantity, 2_f64);
+        assert_eq!(material.limit, Some(3000));
+        assert_eq!(chain.crafts_per_limit, Some(1500));
+    }
+
+    #[test]
+    fn cuts_two_item_cycles() {
+        // 1 is made from 2 and 2 from 1, so one of them has to be bought
+        let crafting = vec![recipe(1, 1, &[(2, 1)]), recipe(2, 1, &[(1, 1)])];
+        let map = maps(&[1, 2]);
+        let ge = prices(&[(1, 100), (2, 40)]);
+
+        let chains = ChainResolver::new(&crafting, &map, &ge)
+            .resolve_all(&HashMap::new(), &GeTax::default());
+        assert_eq!(chains.len(), 2);
+
+        let one = chains.iter().find(|d| d.id == 1).unwrap();
+        assert_eq!(one.chain_cost, 40);
+        assert!(!one.tree.materials[0].crafted);
+
+        // 2 is made from 1, which is cheapest made from 2 bought back
+        let two = chains.iter().find(|d| d.id == 2).unwrap();
+        assert_eq!(two.chain_cost, 40);
+        let one_in_two = &two.tree.materials[0];
+        assert!(one_in_two.crafted);
+        assert_eq!(one_in_two.materials[0].id, 2);
+        assert!(!one_in_two.materials[0].crafted);
+    }
+
+    #[test]
+    fn splits_recipes_that_make_several_items() {
+        // 2 is made three at a time from one of 3
+        let crafting = vec![recipe(1, 1, &[(2, 1)]), recipe(2, 3, &[(3, 1)])];
+        let map = maps(&[1, 2, 3]);
+        let ge = prices(&[(1, 1_000), (2, 100), (3, 30)]);
+
+        let chain = resolve(&crafting, &map, &ge, 1);
+        assert_eq!(chain.chain_cost, 10);
+
+        let material = &chain.tree.materials[0];
+        assert!(material.crafted);
+        assert_eq!(material.quantity, 1_f64);
+        assert!((material.crafts - 1_f64 / 3_f64).abs() < 1e-9);
+        assert!((material.materials[0].quantity - 1_f64 / 3_f64).abs() < 1e-9);
+        assert!((material.cost - 10_f64).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::repo::sql::DatabaseErrors;
+
+use std::fmt;
+
+/// Everything that can go wrong while fetching, storing or deriving OSRS data.
+#[derive(Debug)]
+pub enum OsrsError {
+    /// The upstream could not be reached or returned an error status.
+    Network(String),
+    /// The upstream answered with something we could not decode.
+    Decode(String),
+    /// A price every calculation depends on (e.g. nature runes) is not known yet.
+    MissingReferencePrice(i64),
+    Database(DatabaseErrors),
+}
+
+impl fmt::Display for OsrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OsrsError::Network(e) => write!(f, "network error: {}", e),
+            OsrsError::Decode(e) => write!(f, "decode error: {}", e),
+            OsrsError::MissingReferencePrice(e) => write!(f, "no price for item {}", e),
+            OsrsError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OsrsError {}
+
+impl From<DatabaseErrors> for OsrsError {
+    fn from(e: DatabaseErrors) -> Self {
+        OsrsError::Database(e)
+    }
+}
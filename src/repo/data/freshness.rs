@@ -0,0 +1,99 @@
+use std::env;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+
+const DEFAULT_STALE_AFTER_MINUTES: i64 = 60;
+
+/// How current the cached data is: when the last refresh ran, whether it worked and how old
+/// a trade may be before the prices built from it are flagged as stale.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Freshness {
+    pub last_success: Option<NaiveDateTime>,
+    pub last_attempt: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub stale_after_minutes: i64,
+}
+
+impl Default for Freshness {
+    fn default() -> Self {
+        Freshness {
+            last_success: None,
+            last_attempt: None,
+            last_error: None,
+            stale_after_minutes: DEFAULT_STALE_AFTER_MINUTES,
+        }
+    }
+}
+
+impl Freshness {
+    /// Reads the stale age from `STALE_AFTER_MINUTES`, defaulting to an hour.
+    pub fn from_env() -> Self {
+        let mut freshness = Freshness::default();
+
+        if let Ok(e) = env::var("STALE_AFTER_MINUTES") {
+            match e.parse() {
+                Ok(v) if v > 0 => freshness.stale_after_minutes = v,
+                _ => println!("Ignoring invalid value \"{}\" for STALE_AFTER_MINUTES", e),
+            }
+        }
+
+        freshness
+    }
+
+    /// "updated 3 minutes ago", or why there is nothing to show yet.
+    pub fn updated_ago(&self) -> String {
+        match self.last_success {
+            Some(e) => format!("updated {}", ago(Utc::now().naive_utc() - e)),
+            None => match self.last_attempt {
+                Some(_) => "not loaded yet".to_string(),
+                None => "loading".to_string(),
+            },
+        }
+    }
+
+    /// Whether the most recent refresh attempt failed.
+    pub fn failing(&self) -> bool {
+        self.last_error.is_some()
+    }
+
+    /// A price whose last trade (a unix timestamp) is older than the stale age. Prices that
+    /// have never traded are stale too.
+    pub fn is_stale(&self, last_trade: &Option<i64>) -> bool {
+        match last_trade {
+            Some(e) => Utc::now().timestamp() - e > self.stale_after_minutes * 60,
+            None => true,
+        }
+    }
+
+    /// "12 minutes ago" for a unix timestamp, or "-" if there is none.
+    pub fn trade_ago(&self, last_trade: &Option<i64>) -> String {
+        match last_trade.and_then(|e| DateTime::from_timestamp(e, 0)) {
+            Some(e) => ago(Utc::now() - e),
+            None => "-".to_string(),
+        }
+    }
+}
+
+fn ago(d: chrono::Duration) -> String {
+    let minutes = d.num_minutes();
+
+    if minutes < 1 {
+        return "just now".to_string();
+    }
+    if minutes < 60 {
+        return plural(minutes, "minute");
+    }
+    if minutes < 48 * 60 {
+        return plural(d.num_hours(), "hour");
+    }
+    plural(d.num_days(), "day")
+}
+
+fn plural(n: i64, unit: &str) -> String {
+    match n {
+        1 => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", n, unit),
+    }
+}
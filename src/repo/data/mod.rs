@@ -1 +1,11 @@
+pub mod alert;
+pub mod chain;
+pub mod error;
+pub mod freshness;
 pub mod osrs;
+pub mod pricing;
+pub mod profile;
+pub mod spell;
+pub mod table;
+pub mod tax;
+pub mod upstream;
@@ -1,35 +1,367 @@
+use crate::repo::data::alert::{AlertKind, AlertRule, Webhook, BASIS_POINTS_PER_PERCENT};
+use crate::repo::data::chain::{ChainProfit, ChainResolver};
+use crate::repo::data::error::OsrsError;
+use crate::repo::data::freshness::Freshness;
+use crate::repo::data::pricing::Pricing;
+use crate::repo::data::spell::{
+    gen_spell_profit, RuneSetup, SpellProfit, HIGH_ALCH_RUNES, LOW_ALCH_RUNES,
+};
+use crate::repo::data::tax::GeTax;
+use crate::repo::data::upstream::Upstream;
 use crate::Database;
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use reqwest::header::USER_AGENT;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use serde_json::Value;
 use tokio;
+use tokio::sync::broadcast;
 
 #[derive(Clone)]
 pub struct Osrs {
     maps: Arc<Mutex<HashMap<i64, OsrsMap>>>,
     ge: Arc<Mutex<HashMap<i64, GePrice>>>,
+    volume_5m: Arc<Mutex<HashMap<i64, GeVolume>>>,
+    volume_1h: Arc<Mutex<HashMap<i64, GeVolume>>>,
     high_alch_profit: Arc<Mutex<Vec<HighAlchProfit>>>,
     low_alch_profit: Arc<Mutex<Vec<LowAlchProfit>>>,
-    crafting: Arc<Vec<CraftingItem>>,
+    flipping: Arc<Mutex<Vec<FlipProfit>>>,
+    crafting: Arc<Mutex<Vec<CraftingItem>>>,
     crafting_profit: Arc<Mutex<Vec<CraftingItemProfit>>>,
+    chains: Arc<Mutex<Vec<ChainProfit>>>,
+    spells: Arc<Mutex<Vec<SpellProfit>>>,
+    tax: Arc<GeTax>,
+    freshness: Arc<Mutex<Freshness>>,
+    /// Last price written to `ge.price` per item, so unchanged prices are not stored again.
+    last_stored: Arc<Mutex<HashMap<i64, GePrice>>>,
+    upstream: Arc<Upstream>,
+    webhook: Arc<Webhook>,
+    /// Sends the new freshness after every scheduled refresh, for the live-updating pages.
+    updates: broadcast::Sender<Freshness>,
     database: Database,
 }
 
 impl Osrs {
-    pub async fn new(database: Database) -> Result<Self, String> {
-        let data = match Osrs::fetch_maps().await {
+    /// Builds the caches and starts the refresh loop. Upstream failures are logged and retried
+    /// by the loop rather than aborting startup, so the site can come up while the wiki is down.
+    pub async fn new(
+        database: Database,
+        tax: GeTax,
+        upstream: Upstream,
+        freshness: Freshness,
+        webhook: Webhook,
+    ) -> Self {
+        let osrs = Osrs {
+            maps: Arc::new(Mutex::new(HashMap::new())),
+            ge: Arc::new(Mutex::new(HashMap::new())),
+            volume_5m: Arc::new(Mutex::new(HashMap::new())),
+            volume_1h: Arc::new(Mutex::new(HashMap::new())),
+            high_alch_profit: Arc::new(Mutex::new(Vec::new())),
+            low_alch_profit: Arc::new(Mutex::new(Vec::new())),
+            flipping: Arc::new(Mutex::new(Vec::new())),
+            crafting: Arc::new(Mutex::new(Vec::new())),
+            crafting_profit: Arc::new(Mutex::new(Vec::new())),
+            chains: Arc::new(Mutex::new(Vec::new())),
+            spells: Arc::new(Mutex::new(Vec::new())),
+            tax: Arc::new(tax),
+            freshness: Arc::new(Mutex::new(freshness)),
+            last_stored: Arc::new(Mutex::new(HashMap::new())),
+            upstream: Arc::new(upstream),
+            webhook: Arc::new(webhook),
+            updates: broadcast::channel(16).0,
+            database,
+        };
+
+        // Without this every restart would store a full duplicate snapshot
+        match osrs.database.get_latest_ge_prices().await {
+            Ok(e) => *osrs.last_stored.lock().unwrap() = e,
+            Err(e) => println!("cannot load last stored prices: {}", e),
+        };
+
+        // Stored recipes are used straight away, the wiki is only asked by `recipe_schedule`
+        match osrs.database.get_recipes().await {
+            Ok(e) => {
+                println!("loaded {} stored recipes", e.len());
+                *osrs.crafting.lock().unwrap() = e;
+            }
+            Err(e) => println!("cannot load stored recipes: {}", e),
+        };
+
+        match osrs.refresh_recorded().await {
+            Ok(_) => (),
+            Err(e) => println!("cannot load initial cache: {}", e),
+        };
+
+        let osrs_copy = osrs.clone();
+
+        tokio::spawn(async move {
+            osrs_copy.update_schedule().await;
+        });
+
+        let osrs_copy = osrs.clone();
+
+        tokio::spawn(async move {
+            osrs_copy.recipe_schedule().await;
+        });
+
+        return osrs;
+    }
+
+    /// Fetches and parses the production recipes, stores them along with a log of what
+    /// changed and swaps them into the cache.
+    async fn refresh_recipes(&self) -> Result<(), OsrsError> {
+        let ci_temp = match Osrs::fetch_crafting(&self.upstream).await {
             Ok(e) => e,
             Err(e) => return Err(e),
         };
 
+        let mut recipes = Osrs::convert_crafting(ci_temp, self.get_maps_all());
+
+        // An empty parse means the wiki or the mappings are broken, not that every recipe
+        // was removed, so keep what is stored.
+        if recipes.is_empty() {
+            return Err(OsrsError::Decode("no recipes could be parsed".to_string()));
+        }
+
+        Osrs::assign_recipe_keys(&mut recipes);
+
+        let changes = match self.database.save_recipes(&recipes).await {
+            Ok(e) => e,
+            Err(e) => return Err(OsrsError::from(e)),
+        };
+
+        // The first load adds every recipe, which is not worth listing
+        if changes.len() <= 50 {
+            for c in &changes {
+                println!("recipe {} {}", c.key, c.change);
+            }
+        }
+        println!(
+            "stored {} recipes, {} changed",
+            recipes.len(),
+            changes.len()
+        );
+
+        *self.crafting.lock().unwrap() = recipes;
+
+        // Don't wait for the next price refresh to show the new recipes
+        let maps = self.get_maps_all();
+        let crafting = Osrs::with_limits(self.get_crafting(), &maps);
+        let ge = self.get_ge_all();
+        let volume_1h = self.get_volume_all(&VolumeTimestep::OneHour);
+
+        let ci_ge = Osrs::convert_crafting_profit(
+            &crafting,
+            ge.clone(),
+            &volume_1h,
+            &self.tax,
+            &Pricing::default(),
+        );
+        let chains = ChainResolver::new(&crafting, &maps, &ge).resolve_all(&volume_1h, &self.tax);
+        let spells = gen_spell_profit(
+            &crafting,
+            &maps,
+            &ge,
+            &volume_1h,
+            &self.tax,
+            &Pricing::default(),
+            &RuneSetup::default(),
+        );
+
+        *self.crafting_profit.lock().unwrap() = ci_ge;
+        *self.chains.lock().unwrap() = chains;
+        *self.spells.lock().unwrap() = spells;
+
+        return Ok(());
+    }
+
+    /// Refetches recipes every `recipe_refresh_hours`, counted from the last stored check so
+    /// restarts don't trigger a fetch. Retries every five minutes while nothing is stored.
+    async fn recipe_schedule(&self) {
+        let interval = Duration::from_secs(self.upstream.recipe_refresh_hours * 3600);
+
+        loop {
+            let wait = match self.database.get_recipes_checked().await {
+                Ok(Some(e)) => {
+                    let age = (Utc::now().naive_utc() - e).to_std().unwrap_or_default();
+                    interval.saturating_sub(age)
+                }
+                Ok(None) => Duration::ZERO,
+                Err(e) => {
+                    println!("{}", e);
+                    interval
+                }
+            };
+            tokio::time::sleep(wait).await;
+
+            println!("updating recipes");
+
+            match self.refresh_recipes().await {
+                Ok(_) => (),
+                Err(e) => {
+                    println!("cannot update recipes: {}", e);
+                    tokio::time::sleep(Duration::from_secs(300)).await;
+                }
+            };
+        }
+    }
+
+    /// Gives every recipe a key that stays the same across refreshes: the output item, its
+    /// materials and facilities. When the wiki lists the same combination twice the key also
+    /// gets the output count and skill requirements that tell them apart, and only recipes
+    /// alike in all of that are numbered.
+    fn assign_recipe_keys(recipes: &mut [CraftingItem]) {
+        let bases: Vec<String> = recipes
+            .iter()
+            .map(|r| {
+                format!(
+                    "{}:{}:{}",
+                    r.id,
+                    r.materials
+                        .iter()
+                        .map(|m| format!("{}x{}", m.count, m.id))
+                        .collect::<Vec<String>>()
+                        .join(","),
+                    r.facilities.clone().unwrap_or_default()
+                )
+            })
+            .collect();
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for b in &bases {
+            *counts.entry(b.as_str()).or_insert(0) += 1;
+        }
+
+        let keys: Vec<String> = recipes
+            .iter()
+            .zip(&bases)
+            .map(|(r, b)| match counts[b.as_str()] {
+                1 => b.clone(),
+                _ => format!(
+                    "{}:{}x:{}",
+                    b,
+                    r.output,
+                    r.skills
+                        .iter()
+                        .map(|s| format!("{} {}", s.name, s.level))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                ),
+            })
+            .collect();
+
+        for (r, key) in recipes.iter_mut().zip(keys) {
+            r.key = key;
+        }
+
+        // The wiki result is a map, so number what is still alike in a fixed order. Names are
+        // compared first as they change far less than the rest of a recipe.
+        recipes.sort_by_cached_key(|d| {
+            (
+                d.key.clone(),
+                d.name.clone(),
+                serde_json::to_string(d).unwrap_or_default(),
+            )
+        });
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for r in recipes.iter_mut() {
+            let n = seen.entry(r.key.clone()).or_insert(0);
+            *n += 1;
+
+            if *n > 1 {
+                r.key = format!("{}#{}", r.key, n);
+            }
+        }
+    }
+
+    /// Fills in material buy limits from the current mappings, which can change without the
+    /// recipe changing.
+    fn with_limits(
+        mut crafting: Vec<CraftingItem>,
+        map: &HashMap<i64, OsrsMap>,
+    ) -> Vec<CraftingItem> {
+        for c in crafting.iter_mut() {
+            for m in c.materials.iter_mut() {
+                m.limit = map.get(&m.id).and_then(|e| e.limit);
+            }
+        }
+
+        return crafting;
+    }
+
+    /// Runs `refresh` and records when it ran and how it went. A refresh that swapped the
+    /// caches but could not store the prices still counts as a success, with its error kept.
+    async fn refresh_recorded(&self) -> Result<(), OsrsError> {
+        let attempt = Utc::now().naive_utc();
+        let res = self.refresh().await;
+
+        let mut freshness = self.freshness.lock().unwrap();
+        freshness.last_attempt = Some(attempt);
+        match &res {
+            Ok(e) => {
+                freshness.last_success = Some(attempt);
+                freshness.last_error = e.as_ref().map(|e| e.to_string());
+            }
+            Err(e) => freshness.last_error = Some(e.to_string()),
+        };
+        drop(freshness);
+
+        return res.map(|_| ());
+    }
+
+    /// Prices whose trade times differ from the last row stored for the item.
+    fn changed_prices(&self, ge: &HashMap<i64, GePrice>) -> HashMap<i64, GePrice> {
+        let last_stored = self.last_stored.lock().unwrap();
+
+        return ge
+            .iter()
+            .filter(|(k, d)| match last_stored.get(k) {
+                Some(e) => e.high_time != d.high_time || e.low_time != d.low_time,
+                None => true,
+            })
+            .map(|(k, d)| (*k, d.clone()))
+            .collect();
+    }
+
+    /// Fetches mappings, prices and volumes, stores them and swaps every cache. Failing to
+    /// store the prices doesn't stop the caches being swapped, that error is returned in `Ok`.
+    async fn refresh(&self) -> Result<Option<OsrsError>, OsrsError> {
+        let data = match Osrs::fetch_maps(&self.upstream).await {
+            Ok(e) => {
+                match self.database.upsert_items(&e).await {
+                    Ok(changes) => {
+                        for c in changes {
+                            println!(
+                                "item {} {} changed from {} to {}",
+                                c.item,
+                                c.field,
+                                c.old_value.unwrap_or_default(),
+                                c.new_value.unwrap_or_default()
+                            );
+                        }
+                    }
+                    Err(e) => println!("{}", e),
+                };
+                e
+            }
+            // Mappings rarely change, so the stored copy is good enough until the wiki is back
+            Err(e) => match self.database.get_items().await {
+                Ok(stored) if !stored.is_empty() => {
+                    println!("{}, using {} stored items", e, stored.len());
+                    stored
+                }
+                _ => return Err(e),
+            },
+        };
+
         let mut temp_ge_map: HashMap<i64, OsrsMap> = HashMap::new();
 
         for thing in data {
@@ -38,7 +370,14 @@ impl Osrs {
             temp_ge_map.insert(temp, thing.clone());
         }
 
-        let data = match Osrs::fetch_ge().await {
+        // Swapped in straight away so item pages keep working even if fetching prices fails.
+        // The guard must be scoped rather than dropped for the refresh future to stay Send.
+        {
+            let mut maps_mut = self.maps.lock().unwrap();
+            *maps_mut = temp_ge_map.clone();
+        }
+
+        let data = match Osrs::fetch_ge(&self.upstream).await {
             Ok(e) => e,
             Err(e) => return Err(e),
         };
@@ -46,96 +385,165 @@ impl Osrs {
         let mut temp_map: HashMap<i64, GePrice> = HashMap::new();
 
         for (k, d) in data.iter() {
-            let temp: i64 = k.clone().parse().unwrap();
-
-            let mut temp_data = d.clone();
-
-            match temp_data.low {
-                Some(e) => match temp_data.high {
-                    Some(g) => {
-                        if e > g {
-                            temp_data.high = Some(e.clone());
-                        }
-                    }
-                    None => (),
-                },
-                None => (),
+            let temp: i64 = match k.parse() {
+                Ok(e) => e,
+                Err(_) => {
+                    println!("Skipping price for invalid item id \"{}\"", k);
+                    continue;
+                }
             };
 
-            temp_map.insert(temp, temp_data);
+            // Kept exactly as reported, an instant-sell above instant-buy is a real inverted margin
+            temp_map.insert(temp, d.clone());
         }
 
-        match database.insert_ge_price_bulk(&temp_map).await {
-            Ok(_) => (),
-            Err(e) => return Err("Cannot insert_ge_price_bulk".to_string()),
+        let changed = self.changed_prices(&temp_map);
+
+        // A failed write still refreshes the tables, the unsaved prices count as changed and
+        // are written again on the next refresh
+        let store_error = match self.database.insert_ge_price_bulk(&changed).await {
+            Ok(e) => {
+                println!("stored {} of {} prices", e, temp_map.len());
+                self.last_stored.lock().unwrap().extend(changed);
+                None
+            }
+            Err(e) => {
+                println!("cannot store prices: {}", e);
+                Some(OsrsError::from(e))
+            }
         };
 
-        let hap = Osrs::gen_high_alch_profit(&temp_map, &temp_ge_map);
-        let lap = Osrs::gen_low_alch_profit(&temp_map, &temp_ge_map);
-        let ci_temp = Osrs::fetch_crafting().await;
-        let ci = Osrs::convert_crafting(ci_temp, temp_ge_map.clone());
-        let ci_ge = Osrs::convert_crafting_profit(&ci, temp_map.clone());
+        // Volumes only enrich the tables, so a failed fetch keeps the previous values
+        let mut volumes: HashMap<VolumeTimestep, HashMap<i64, GeVolume>> = HashMap::new();
 
-        let crafting_clone = ci.clone();
+        for timestep in [VolumeTimestep::FiveMinute, VolumeTimestep::OneHour] {
+            let (bucket, volume) = match Osrs::fetch_volume(&self.upstream, &timestep).await {
+                Ok(e) => e,
+                Err(e) => {
+                    println!("{}", e);
+                    volumes.insert(timestep, self.get_volume_all(&timestep));
+                    continue;
+                }
+            };
 
-        let maps = Arc::new(Mutex::new(temp_ge_map));
-        let high_alch_profit = Arc::new(Mutex::new(hap));
-        let low_alch_profit = Arc::new(Mutex::new(lap));
-        let ge = Arc::new(Mutex::new(temp_map));
-        let crafting = Arc::new(ci);
-        let crafting_profit = Arc::new(Mutex::new(ci_ge));
+            match self
+                .database
+                .insert_ge_volume_bulk(&timestep, &bucket, &volume)
+                .await
+            {
+                Ok(_) => (),
+                Err(e) => println!("{}", e),
+            };
 
-        let maps_copy = maps.clone();
-        let high_alch_profit_copy = high_alch_profit.clone();
-        let low_alch_profit_copy = low_alch_profit.clone();
-        let ge_copy = ge.clone();
-        let database_copy = database.clone();
-        let crafting_profit_copy = crafting_profit.clone();
+            volumes.insert(timestep, volume);
+        }
 
-        tokio::spawn(async move {
-            Osrs::update_schedule(
-                maps_copy,
-                ge_copy,
-                high_alch_profit_copy,
-                low_alch_profit_copy,
-                crafting_clone,
-                crafting_profit_copy,
-                database_copy,
-            )
-            .await;
-        });
+        let volume_5m = volumes
+            .remove(&VolumeTimestep::FiveMinute)
+            .unwrap_or_default();
+        let volume_1h = volumes.remove(&VolumeTimestep::OneHour).unwrap_or_default();
 
-        return Ok(Osrs {
-            maps,
-            high_alch_profit,
-            low_alch_profit,
-            ge,
-            database,
-            crafting_profit,
-            crafting,
-        });
+        // The cached tables use the default pricing, any other basis is worked out per request
+        let pricing = Pricing::default();
+
+        let setup = RuneSetup::default();
+
+        let hap =
+            match Osrs::gen_high_alch_profit(&temp_map, &temp_ge_map, &volume_1h, &pricing, &setup)
+            {
+                Ok(e) => e,
+                Err(e) => return Err(e),
+            };
+        let lap = match Osrs::gen_low_alch_profit(
+            &temp_map,
+            &temp_ge_map,
+            &volume_1h,
+            &pricing,
+            &setup,
+        ) {
+            Ok(e) => e,
+            Err(e) => return Err(e),
+        };
+
+        let flips = Osrs::gen_flipping(&temp_map, &temp_ge_map, &volume_1h, &self.tax);
+
+        let crafting = Osrs::with_limits(self.get_crafting(), &temp_ge_map);
+
+        let ci_ge = Osrs::convert_crafting_profit(
+            &crafting,
+            temp_map.clone(),
+            &volume_1h,
+            &self.tax,
+            &pricing,
+        );
+        let chains = ChainResolver::new(&crafting, &temp_ge_map, &temp_map)
+            .resolve_all(&volume_1h, &self.tax);
+        let spells = gen_spell_profit(
+            &crafting,
+            &temp_ge_map,
+            &temp_map,
+            &volume_1h,
+            &self.tax,
+            &pricing,
+            &setup,
+        );
+
+        let mut ge_mut = self.ge.lock().unwrap();
+        *ge_mut = temp_map;
+        drop(ge_mut);
+
+        let mut volume_5m_mut = self.volume_5m.lock().unwrap();
+        *volume_5m_mut = volume_5m;
+        drop(volume_5m_mut);
+
+        let mut volume_1h_mut = self.volume_1h.lock().unwrap();
+        *volume_1h_mut = volume_1h;
+        drop(volume_1h_mut);
+
+        let mut hap_mut = self.high_alch_profit.lock().unwrap();
+        *hap_mut = hap;
+        drop(hap_mut);
+
+        let mut lap_mut = self.low_alch_profit.lock().unwrap();
+        *lap_mut = lap;
+        drop(lap_mut);
+
+        let mut flips_mut = self.flipping.lock().unwrap();
+        *flips_mut = flips;
+        drop(flips_mut);
+
+        let mut ci_ge_mut = self.crafting_profit.lock().unwrap();
+        *ci_ge_mut = ci_ge;
+        drop(ci_ge_mut);
+
+        let mut chains_mut = self.chains.lock().unwrap();
+        *chains_mut = chains;
+        drop(chains_mut);
+
+        let mut spells_mut = self.spells.lock().unwrap();
+        *spells_mut = spells;
+        drop(spells_mut);
+
+        return Ok(store_error);
     }
+
     fn gen_low_alch_profit(
         ge: &HashMap<i64, GePrice>,
         map: &HashMap<i64, OsrsMap>,
-    ) -> Vec<LowAlchProfit> {
+        volume: &HashMap<i64, GeVolume>,
+        pricing: &Pricing,
+        setup: &RuneSetup,
+    ) -> Result<Vec<LowAlchProfit>, OsrsError> {
         let mut temp_vec: Vec<LowAlchProfit> = Vec::new();
-        let nr_price = match ge.get(&561_i64) {
-            Some(e) => match e.high {
-                Some(e) => e,
-                None => panic!("no nature ruin price"),
-            },
-
-            None => panic!("no nature ruin price"),
+        let cast_cost = match setup.cast_cost(&LOW_ALCH_RUNES, ge, volume) {
+            Ok(e) => e,
+            Err(e) => return Err(e),
         };
 
         for (ge_k, ge_d) in ge.iter() {
-            let price = match ge_d.high {
-                Some(e) => e.clone(),
-                None => match ge_d.low {
-                    Some(e) => e.clone(),
-                    None => continue,
-                },
+            let price = match pricing.buy.price(ge_d, volume.get(ge_k)) {
+                Some(e) => e,
+                None => continue,
             };
 
             let map_d = match map.get(ge_k) {
@@ -148,16 +556,23 @@ impl Osrs {
                 None => continue,
             };
 
-            if low_alch < (price + nr_price) {
+            if low_alch < (price + cast_cost) {
                 continue;
             }
 
-            let profit: i64 = (((low_alch - (price + nr_price)) as f64 / low_alch as f64) * 100_f64)
+            let profit: i64 = (((low_alch - (price + cast_cost)) as f64 / low_alch as f64)
+                * 100_f64)
                 .round() as i64;
 
+            let profit_per_use = low_alch as i64 - (price as i64 + cast_cost as i64);
+
             temp_vec.push(LowAlchProfit {
                 profit_percent: profit,
-                profit_per_use: (low_alch as i64 - (price as i64 + nr_price as i64)),
+                profit_per_use,
+                limit: map_d.limit,
+                limit_profit: map_d.limit.map(|l| l * profit_per_use),
+                volume: volume.get(ge_k).map(|v| v.total()),
+                last_trade: pricing.buy.last_trade(ge_d),
                 ge_val: price,
                 lowalch: low_alch,
                 name: map_d.name.clone(),
@@ -170,29 +585,25 @@ impl Osrs {
         temp_vec.sort_by_key(|d| d.profit_per_use);
         temp_vec.reverse();
 
-        return temp_vec;
+        return Ok(temp_vec);
     }
     fn gen_high_alch_profit(
         ge: &HashMap<i64, GePrice>,
         map: &HashMap<i64, OsrsMap>,
-    ) -> Vec<HighAlchProfit> {
+        volume: &HashMap<i64, GeVolume>,
+        pricing: &Pricing,
+        setup: &RuneSetup,
+    ) -> Result<Vec<HighAlchProfit>, OsrsError> {
         let mut temp_vec: Vec<HighAlchProfit> = Vec::new();
-        let nr_price = match ge.get(&561_i64) {
-            Some(e) => match e.high {
-                Some(e) => e,
-                None => panic!("no nature ruin price"),
-            },
-
-            None => panic!("no nature ruin price"),
+        let cast_cost = match setup.cast_cost(&HIGH_ALCH_RUNES, ge, volume) {
+            Ok(e) => e,
+            Err(e) => return Err(e),
         };
 
         for (ge_k, ge_d) in ge.iter() {
-            let price = match ge_d.high {
-                Some(e) => e.clone(),
-                None => match ge_d.low {
-                    Some(e) => e.clone(),
-                    None => continue,
-                },
+            let price = match pricing.buy.price(ge_d, volume.get(ge_k)) {
+                Some(e) => e,
+                None => continue,
             };
 
             let map_d = match map.get(ge_k) {
@@ -205,17 +616,23 @@ impl Osrs {
                 None => continue,
             };
 
-            if high_alch < (price + nr_price) {
+            if high_alch < (price + cast_cost) {
                 continue;
             }
 
-            let profit: i64 = (((high_alch - (price + nr_price)) as f64 / high_alch as f64)
+            let profit: i64 = (((high_alch - (price + cast_cost)) as f64 / high_alch as f64)
                 * 100_f64)
                 .round() as i64;
 
+            let profit_per_use = high_alch as i64 - (price as i64 + cast_cost as i64);
+
             temp_vec.push(HighAlchProfit {
                 profit_percent: profit,
-                profit_per_use: (high_alch as i64 - (price as i64 + nr_price as i64)),
+                profit_per_use,
+                limit: map_d.limit,
+                limit_profit: map_d.limit.map(|l| l * profit_per_use),
+                volume: volume.get(ge_k).map(|v| v.total()),
+                last_trade: pricing.buy.last_trade(ge_d),
                 ge_val: price,
                 highalch: high_alch,
                 name: map_d.name.clone(),
@@ -228,148 +645,312 @@ impl Osrs {
         temp_vec.sort_by_key(|d| d.profit_per_use);
         temp_vec.reverse();
 
+        return Ok(temp_vec);
+    }
+
+    /// Buying at the instant-sell `low` and selling at the instant-buy `high`, for every item
+    /// with both prices. Inverted books (low above high) are kept and show a negative spread.
+    fn gen_flipping(
+        ge: &HashMap<i64, GePrice>,
+        map: &HashMap<i64, OsrsMap>,
+        volume: &HashMap<i64, GeVolume>,
+        tax: &GeTax,
+    ) -> Vec<FlipProfit> {
+        let mut temp_vec: Vec<FlipProfit> = Vec::new();
+
+        for (ge_k, ge_d) in ge.iter() {
+            let (high, low) = match (ge_d.high, ge_d.low) {
+                (Some(h), Some(l)) => (h, l),
+                _ => continue,
+            };
+
+            let map_d = match map.get(ge_k) {
+                Some(e) => e,
+                None => continue,
+            };
+
+            let sell_tax = tax.tax(ge_k, high);
+            let margin = high - sell_tax - low;
+
+            temp_vec.push(FlipProfit {
+                name: map_d.name.clone(),
+                id: map_d.id,
+                icon: map_d.icon.clone(),
+                members: map_d.members,
+                high,
+                low,
+                high_time: ge_d.high_time,
+                low_time: ge_d.low_time,
+                spread: high - low,
+                tax: sell_tax,
+                margin,
+                roi: match low {
+                    0 => 0_f64,
+                    _ => margin as f64 * 100_f64 / low as f64,
+                },
+                inverted: low > high,
+                limit: map_d.limit,
+                limit_profit: map_d.limit.map(|l| l * margin),
+                volume: volume.get(ge_k).map(|v| v.total()),
+                // A margin is only as recent as the older of its two prices
+                last_trade: ge_d.last_trade(),
+            })
+        }
+
+        temp_vec.sort_by_key(|d| d.margin);
+        temp_vec.reverse();
+
         return temp_vec;
     }
 
-    async fn fetch_maps() -> Result<OsrsMapsRaw, String> {
+    /// GETs `url` from the upstream and decodes the JSON body. `what` names the data in errors.
+    async fn fetch_json<T: DeserializeOwned>(
+        upstream: &Upstream,
+        url: String,
+        what: &str,
+    ) -> Result<T, OsrsError> {
         let client = reqwest::Client::new();
 
         let res = match client
-            .get("https://prices.runescape.wiki/api/v1/osrs/mapping")
-            .header(USER_AGENT, "gecalculator - ellabella on discord")
+            .get(url)
+            .header(USER_AGENT, &upstream.user_agent)
             .send()
             .await
         {
             Ok(e) => e,
             Err(e) => {
-                println!("Error fetching maps: {:?}", e);
-                return Err("Couldn't fetch mappings".to_string());
+                println!("Error fetching {}: {:?}", what, e);
+                return Err(OsrsError::Network(format!("couldn't fetch {}", what)));
             }
         };
 
-        let raw = res.text().await.unwrap();
+        if !res.status().is_success() {
+            return Err(OsrsError::Network(format!(
+                "fetching {} returned {}",
+                what,
+                res.status()
+            )));
+        }
 
-        let obj: OsrsMapsRaw = serde_json::from_str(&raw).unwrap();
+        let raw = match res.text().await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error reading {}: {:?}", what, e);
+                return Err(OsrsError::Network(format!("couldn't read {}", what)));
+            }
+        };
 
-        return Ok(obj);
+        return match serde_json::from_str(&raw) {
+            Ok(e) => Ok(e),
+            Err(e) => Err(OsrsError::Decode(format!("{}: {}", what, e))),
+        };
     }
 
-    async fn fetch_ge() -> Result<HashMap<String, GePrice>, String> {
-        let client = reqwest::Client::new();
+    pub async fn fetch_maps(upstream: &Upstream) -> Result<OsrsMapsRaw, OsrsError> {
+        return Osrs::fetch_json(upstream, upstream.mapping_url(), "mappings").await;
+    }
 
-        let res = match client
-            .get("https://prices.runescape.wiki/api/v1/osrs/latest")
-            .header(USER_AGENT, "gecalculator - ellabella on discord")
-            .send()
-            .await
+    async fn fetch_ge(upstream: &Upstream) -> Result<HashMap<String, GePrice>, OsrsError> {
+        let obj: OsrsGeData =
+            match Osrs::fetch_json(upstream, upstream.latest_url(), "latest prices").await {
+                Ok(e) => e,
+                Err(e) => return Err(e),
+            };
+
+        return Ok(obj.data);
+    }
+
+    /// Up to 365 averaged buckets of history for one item, oldest first.
+    pub async fn fetch_timeseries(
+        upstream: &Upstream,
+        id: &i64,
+        timestep: &TimeseriesTimestep,
+    ) -> Result<Vec<TimeseriesPoint>, OsrsError> {
+        let obj: OsrsTimeseries = match Osrs::fetch_json(
+            upstream,
+            upstream.timeseries_url(id, timestep.as_str()),
+            &format!("{} timeseries for {}", timestep.as_str(), id),
+        )
+        .await
         {
             Ok(e) => e,
-            Err(e) => {
-                println!("Error fetching maps: {:?}", e);
-                return Err("Couldn't fetch mappings".to_string());
+            Err(e) => return Err(e),
+        };
+
+        return Ok(obj.data);
+    }
+
+    async fn fetch_volume(
+        upstream: &Upstream,
+        timestep: &VolumeTimestep,
+    ) -> Result<(NaiveDateTime, HashMap<i64, GeVolume>), OsrsError> {
+        let obj: OsrsVolumeData = match Osrs::fetch_json(
+            upstream,
+            upstream.volume_url(timestep.as_str()),
+            &format!("{} volume", timestep.as_str()),
+        )
+        .await
+        {
+            Ok(e) => e,
+            Err(e) => return Err(e),
+        };
+
+        let bucket = match DateTime::from_timestamp(obj.timestamp, 0) {
+            Some(e) => e.naive_utc(),
+            None => {
+                return Err(OsrsError::Decode(format!(
+                    "invalid {} volume timestamp {}",
+                    timestep.as_str(),
+                    obj.timestamp
+                )))
             }
         };
 
-        let raw = res.text().await.unwrap();
+        let mut volume: HashMap<i64, GeVolume> = HashMap::new();
 
-        let obj: OsrsGeData = serde_json::from_str(&raw).unwrap();
+        for (k, d) in obj.data {
+            match k.parse() {
+                Ok(e) => {
+                    volume.insert(e, d);
+                }
+                Err(_) => continue,
+            };
+        }
 
-        return Ok(obj.data);
+        return Ok((bucket, volume));
     }
 
-    async fn update_schedule(
-        maps: Arc<Mutex<HashMap<i64, OsrsMap>>>,
-        ge: Arc<Mutex<HashMap<i64, GePrice>>>,
-        high_alch_profit: Arc<Mutex<Vec<HighAlchProfit>>>,
-        low_alch_profit: Arc<Mutex<Vec<LowAlchProfit>>>,
-        crafting: Vec<CraftingItem>,
-        craftting_profit: Arc<Mutex<Vec<CraftingItemProfit>>>,
-        database: Database,
-    ) {
+    async fn update_schedule(&self) {
         println!("starting thread");
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
             println!("updating Cache");
 
-            let data = match Osrs::fetch_maps().await {
-                Ok(e) => e,
+            let updated = match self.refresh_recorded().await {
+                Ok(_) => {
+                    println!("cache updated");
+                    true
+                }
                 Err(e) => {
-                    println!("cannot fetch maps");
-                    continue;
+                    println!("cannot update cache: {}", e);
+                    false
                 }
             };
 
-            let mut temp_ge_map: HashMap<i64, OsrsMap> = HashMap::new();
+            // Sent after failures too so live pages show the refresh error.
+            // Only fails when nobody is listening
+            if let Ok(e) = self.updates.send(self.get_freshness()) {
+                println!("notified {} live pages", e);
+            }
 
-            for thing in data {
-                let temp = thing.id.clone();
+            if updated {
+                self.check_alerts().await;
+            }
+        }
+    }
 
-                temp_ge_map.insert(temp, thing.clone());
+    /// Delivers every alert whose condition started holding since the last refresh and
+    /// re-arms the ones that stopped. Failed deliveries are retried after the next refresh.
+    async fn check_alerts(&self) {
+        let rules = match self.database.get_alerts().await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("cannot load alerts: {}", e);
+                return;
             }
+        };
 
-            let data = match Osrs::fetch_ge().await {
-                Ok(e) => e,
-                Err(e) => {
-                    println!("cannot fetch maps");
-                    continue;
-                }
+        for rule in rules {
+            let value = match self.alert_value(&rule).await {
+                Some(e) => e,
+                None => continue,
             };
 
-            let mut temp_map: HashMap<i64, GePrice> = HashMap::new();
-
-            for (k, d) in data.iter() {
-                let temp: i64 = k.clone().parse().unwrap();
-
-                let mut temp_data = d.clone();
+            let met = rule.kind.is_met(value, rule.threshold);
+            if met == rule.triggered {
+                continue;
+            }
 
-                match temp_data.low {
-                    Some(e) => match temp_data.high {
-                        Some(g) => {
-                            if e > g {
-                                temp_data.high = Some(e.clone());
-                            }
-                        }
-                        None => (),
-                    },
-                    None => (),
+            if met {
+                let name = match self.get_map_one(&rule.item) {
+                    Some(e) => e.name,
+                    None => format!("Item {}", rule.item),
                 };
 
-                temp_map.insert(temp, temp_data);
+                match self.webhook.send(&rule, &name, value).await {
+                    Ok(_) => (),
+                    Err(e) => {
+                        println!("cannot deliver alert {}: {}", rule.id, e);
+                        continue;
+                    }
+                };
             }
 
-            match database.insert_ge_price_bulk(&temp_map).await {
+            match self.database.set_alert_triggered(&rule.id, met).await {
                 Ok(_) => (),
-                Err(_) => print!("Cannot insert_ge_price_bulk"),
+                Err(e) => println!("cannot update alert {}: {}", rule.id, e),
             };
+        }
+    }
 
-            let hap = Osrs::gen_high_alch_profit(&temp_map, &temp_ge_map);
-            let lap = Osrs::gen_low_alch_profit(&temp_map, &temp_ge_map);
-            let ci_ge = Osrs::convert_crafting_profit(&crafting.clone(), temp_map.clone());
-
-            let mut maps_mut = maps.lock().unwrap();
-            *maps_mut = temp_ge_map.clone();
-            drop(maps_mut);
-
-            let mut ge_mut = ge.lock().unwrap();
-            *ge_mut = temp_map;
-            drop(ge_mut);
+    /// The current value an alert compares against its threshold, if it can be worked out.
+    async fn alert_value(&self, rule: &AlertRule) -> Option<i64> {
+        let price = self.get_ge_one(&rule.item)?;
 
-            let mut hap_mut = high_alch_profit.lock().unwrap();
-            *hap_mut = hap;
-            drop(hap_mut);
+        match rule.kind {
+            AlertKind::HighAbove | AlertKind::HighBelow => price.high,
+            AlertKind::LowAbove | AlertKind::LowBelow => price.low,
+            AlertKind::MarginAbove => match (price.high, price.low) {
+                (Some(high), Some(low)) => Some(self.tax.after_tax(&rule.item, high) - low),
+                _ => None,
+            },
+            // Worked out here rather than read from the high alch table, which leaves out
+            // unprofitable items and would keep a fired rule from ever re-arming.
+            AlertKind::AlchProfitAbove => {
+                let high_alch = self.get_map_one(&rule.item)?.highalch?;
+                let volume = self.get_volume_one(&VolumeTimestep::OneHour, &rule.item);
+                let cost = Pricing::default().buy.price(&price, volume.as_ref())?;
+
+                let cast_cost = match self.get_cast_cost(&RuneSetup::default(), true) {
+                    Ok(e) => e,
+                    Err(e) => {
+                        println!("cannot work out cast cost for alert {}: {}", rule.id, e);
+                        return None;
+                    }
+                };
 
-            let mut lap_mut = low_alch_profit.lock().unwrap();
-            *lap_mut = lap;
-            drop(lap_mut);
+                Some(high_alch - (cost + cast_cost))
+            }
+            AlertKind::PercentChange => {
+                let now = price.high?;
+                let at = Utc::now().naive_utc()
+                    - chrono::Duration::minutes(rule.window_minutes.unwrap_or_default());
+
+                let then = match self.database.get_ge_price_at(&rule.item, &at).await {
+                    Ok(Some(e)) => e.high?,
+                    Ok(None) => return None,
+                    Err(e) => {
+                        println!("cannot load earlier price for alert {}: {}", rule.id, e);
+                        return None;
+                    }
+                };
 
-            let mut ci_ge_mut = craftting_profit.lock().unwrap();
-            *ci_ge_mut = ci_ge;
-            drop(ci_ge_mut);
+                if then == 0 {
+                    return None;
+                }
 
-            println!("cache updated");
+                // Truncated rather than rounded so a change just short of the threshold
+                // never counts as reaching it.
+                let percent = (now - then) as f64 * 100_f64 / then as f64;
+                Some((percent * BASIS_POINTS_PER_PERCENT as f64).trunc() as i64)
+            }
         }
     }
 
+    pub fn subscribe(&self) -> broadcast::Receiver<Freshness> {
+        return self.updates.subscribe();
+    }
+
     pub fn get_maps_all(&self) -> HashMap<i64, OsrsMap> {
         let stuff = self.maps.lock().unwrap();
 
@@ -382,21 +963,184 @@ impl Osrs {
         return stuff.clone();
     }
 
+    pub fn get_volume_all(&self, timestep: &VolumeTimestep) -> HashMap<i64, GeVolume> {
+        let stuff = match timestep {
+            VolumeTimestep::FiveMinute => self.volume_5m.lock().unwrap(),
+            VolumeTimestep::OneHour => self.volume_1h.lock().unwrap(),
+        };
+
+        return stuff.clone();
+    }
+
+    pub fn get_volume_one(&self, timestep: &VolumeTimestep, id: &i64) -> Option<GeVolume> {
+        let stuff = match timestep {
+            VolumeTimestep::FiveMinute => self.volume_5m.lock().unwrap(),
+            VolumeTimestep::OneHour => self.volume_1h.lock().unwrap(),
+        };
+
+        return stuff.get(id).cloned();
+    }
+
     pub fn get_high_alch_profit(&self) -> Vec<HighAlchProfit> {
         let stuff = self.high_alch_profit.lock().unwrap();
         return stuff.clone();
     }
 
+    /// The high alch table at `pricing` and `setup`, served from the cache for the defaults.
+    pub fn get_high_alch_profit_priced(
+        &self,
+        pricing: &Pricing,
+        setup: &RuneSetup,
+    ) -> Result<Vec<HighAlchProfit>, OsrsError> {
+        if pricing.is_default() && setup.is_default() {
+            return Ok(self.get_high_alch_profit());
+        }
+
+        return Osrs::gen_high_alch_profit(
+            &self.get_ge_all(),
+            &self.get_maps_all(),
+            &self.get_volume_all(&VolumeTimestep::OneHour),
+            pricing,
+            setup,
+        );
+    }
+
+    /// Rune cost of one High Level Alchemy cast, or Low Level Alchemy when `high` is false.
+    pub fn get_cast_cost(&self, setup: &RuneSetup, high: bool) -> Result<i64, OsrsError> {
+        let runes = match high {
+            true => HIGH_ALCH_RUNES,
+            false => LOW_ALCH_RUNES,
+        };
+
+        return setup.cast_cost(
+            &runes,
+            &self.get_ge_all(),
+            &self.get_volume_all(&VolumeTimestep::OneHour),
+        );
+    }
+
+    pub fn get_flipping(&self) -> Vec<FlipProfit> {
+        let stuff = self.flipping.lock().unwrap();
+        return stuff.clone();
+    }
+
     pub fn get_low_alch_profit(&self) -> Vec<LowAlchProfit> {
         let stuff = self.low_alch_profit.lock().unwrap();
         return stuff.clone();
     }
 
+    /// The low alch table at `pricing` and `setup`, served from the cache for the defaults.
+    pub fn get_low_alch_profit_priced(
+        &self,
+        pricing: &Pricing,
+        setup: &RuneSetup,
+    ) -> Result<Vec<LowAlchProfit>, OsrsError> {
+        if pricing.is_default() && setup.is_default() {
+            return Ok(self.get_low_alch_profit());
+        }
+
+        return Osrs::gen_low_alch_profit(
+            &self.get_ge_all(),
+            &self.get_maps_all(),
+            &self.get_volume_all(&VolumeTimestep::OneHour),
+            pricing,
+            setup,
+        );
+    }
+
+    fn get_crafting(&self) -> Vec<CraftingItem> {
+        return self.crafting.lock().unwrap().clone();
+    }
+
+    pub fn get_chains(&self) -> Vec<ChainProfit> {
+        let stuff = self.chains.lock().unwrap();
+        return stuff.clone();
+    }
+
+    pub fn get_chain(&self, id: &i64) -> Option<ChainProfit> {
+        let stuff = self.chains.lock().unwrap();
+        return stuff.iter().find(|d| d.id == *id).cloned();
+    }
+
     pub fn get_crafting_profit(&self) -> Vec<CraftingItemProfit> {
         let stuff = self.crafting_profit.lock().unwrap();
         return stuff.clone();
     }
 
+    /// The crafting table at `pricing`, served from the cache for the default pricing.
+    pub fn get_crafting_profit_priced(&self, pricing: &Pricing) -> Vec<CraftingItemProfit> {
+        if pricing.is_default() {
+            return self.get_crafting_profit();
+        }
+
+        let crafting = Osrs::with_limits(self.get_crafting(), &self.get_maps_all());
+
+        return Osrs::convert_crafting_profit(
+            &crafting,
+            self.get_ge_all(),
+            &self.get_volume_all(&VolumeTimestep::OneHour),
+            &self.tax,
+            pricing,
+        );
+    }
+
+    pub fn get_spells(&self) -> Vec<SpellProfit> {
+        let stuff = self.spells.lock().unwrap();
+        return stuff.clone();
+    }
+
+    /// Spell casts at `pricing` and `setup`, served from the cache for the defaults.
+    pub fn get_spells_priced(&self, pricing: &Pricing, setup: &RuneSetup) -> Vec<SpellProfit> {
+        if pricing.is_default() && setup.is_default() {
+            return self.get_spells();
+        }
+
+        let crafting = Osrs::with_limits(self.get_crafting(), &self.get_maps_all());
+
+        return gen_spell_profit(
+            &crafting,
+            &self.get_maps_all(),
+            &self.get_ge_all(),
+            &self.get_volume_all(&VolumeTimestep::OneHour),
+            &self.tax,
+            pricing,
+            setup,
+        );
+    }
+
+    /// Every skill some recipe trains or requires, sorted by name.
+    pub fn get_crafting_skills(&self) -> Vec<String> {
+        let stuff = self.crafting_profit.lock().unwrap();
+
+        let mut skills: Vec<String> = stuff
+            .iter()
+            .flat_map(|d| d.skills.iter().map(|s| s.name.clone()))
+            .collect();
+        skills.sort();
+        skills.dedup();
+
+        return skills;
+    }
+
+    pub fn get_map_one(&self, id: &i64) -> Option<OsrsMap> {
+        let stuff = self.maps.lock().unwrap();
+
+        let res = stuff.get(id);
+
+        return match res {
+            Some(e) => Some(e.clone()),
+            None => None,
+        };
+    }
+
+    pub fn get_freshness(&self) -> Freshness {
+        return self.freshness.lock().unwrap().clone();
+    }
+
+    pub fn get_tax(&self) -> Arc<GeTax> {
+        return self.tax.clone();
+    }
+
     pub fn get_ge_one(&self, id: &i64) -> Option<GePrice> {
         let stuff = self.ge.lock().unwrap();
 
@@ -408,7 +1152,9 @@ impl Osrs {
         };
     }
 
-    async fn fetch_crafting() -> HashMap<String, CraftingRequestItem> {
+    async fn fetch_crafting(
+        upstream: &Upstream,
+    ) -> Result<HashMap<String, CraftingRequestItem>, OsrsError> {
         let client = reqwest::Client::new();
 
         let mut offset: usize = 0;
@@ -416,37 +1162,45 @@ impl Osrs {
         let mut items: HashMap<String, CraftingRequestItem> = HashMap::new();
 
         loop {
-            let url = format!("https://oldschool.runescape.wiki/w/Special:Ask/class%3Dsortable-20wikitable-20smwtable/format%3Djson/headers%3Dshow/link%3Dall/mainlabel%3D/searchlabel%3DJSON/sort%3D/order%3Dasc/offset%3D{}/limit%3D500/-5B-5BProduction-20JSON::%2B-5D-5D/-3FProduction-20JSON/prettyprint%3Dtrue/unescape%3Dtrue", offset);
-            let temp_items: CraftingRequest = match client
+            let url = upstream.production_url(offset);
+            let res = match client
                 .get(url)
-                .header(USER_AGENT, "gecalculator - ellabella on discord")
+                .header(USER_AGENT, &upstream.user_agent)
                 .send()
                 .await
             {
-                Ok(e) => {
-                    let text = e.text().await.unwrap();
-                    if text.len() <= 5 {
-                        break;
-                    }
-                    match serde_json::from_str(&text) {
-                        Ok(i) => i,
-
-                        Err(i) => panic!("Can't deserialize json {:?}", i),
-                    }
+                Ok(e) => e,
+                Err(e) => {
+                    println!("Error fetching crafting: {:?}", e);
+                    return Err(OsrsError::Network("couldn't fetch crafting".to_string()));
                 }
+            };
+
+            let text = match res.text().await {
+                Ok(e) => e,
                 Err(e) => {
-                    println!("Error fetching maps: {:?}", e);
-                    break;
+                    println!("Error reading crafting: {:?}", e);
+                    return Err(OsrsError::Network("couldn't read crafting".to_string()));
                 }
             };
 
+            // The wiki answers an exhausted query with an empty array
+            if text.len() <= 5 {
+                break;
+            }
+
+            let temp_items: CraftingRequest = match serde_json::from_str(&text) {
+                Ok(e) => e,
+                Err(e) => return Err(OsrsError::Decode(format!("crafting: {}", e))),
+            };
+
             offset = offset + temp_items.rows as usize;
             for (k, i) in temp_items.results {
                 items.insert(k, i);
             }
         }
 
-        return items;
+        return Ok(items);
     }
 
     fn convert_crafting(
@@ -508,6 +1262,7 @@ impl Osrs {
                         name: mat_map.name.clone(),
                         id: mat_map.id.clone(),
                         icon: mat_map.icon.clone(),
+                        limit: mat_map.limit,
                         count: match m.quantity.clone().parse() {
                             Ok(e) => e,
                             Err(_) => {
@@ -530,6 +1285,7 @@ impl Osrs {
                 }
 
                 crafting_items.push(CraftingItem {
+                    key: String::new(),
                     name: item_map.name.clone(),
                     icon: item_map.icon.clone(),
                     id: item_map.id,
@@ -557,6 +1313,9 @@ impl Osrs {
     fn convert_crafting_profit(
         crafting_items: &Vec<CraftingItem>,
         ge: HashMap<i64, GePrice>,
+        volume: &HashMap<i64, GeVolume>,
+        tax: &GeTax,
+        pricing: &Pricing,
     ) -> Vec<CraftingItemProfit> {
         let mut res: Vec<CraftingItemProfit> = Vec::new();
 
@@ -566,7 +1325,7 @@ impl Osrs {
                 None => continue,
             };
 
-            let price = match gedata.high {
+            let price = match pricing.sell.price(gedata, volume.get(&c.id)) {
                 Some(e) => e,
                 None => continue,
             };
@@ -584,8 +1343,9 @@ impl Osrs {
                     icon: m.icon.clone(),
                     id: m.id.clone(),
                     count: m.count.clone(),
-                    cost: match matgedata.high {
-                        Some(e) => e.clone(),
+                    limit: m.limit,
+                    cost: match pricing.buy.price(matgedata, volume.get(&m.id)) {
+                        Some(e) => e,
                         None => continue,
                     },
                 });
@@ -605,6 +1365,45 @@ impl Osrs {
 
             let profit_margin =
                 ((profit as f64 / (price as f64 * c.output as f64)) * 100_f64).round() as f32;
+
+            let item_tax = tax.tax(&c.id, price) * c.output as i64;
+            let profit_after_tax = tax.after_tax(&c.id, price) * c.output as i64 - total_cost;
+            let profit_margin_after_tax =
+                ((profit_after_tax as f64 / (price as f64 * c.output as f64)) * 100_f64).round()
+                    as f32;
+
+            // The material whose buy limit runs out first caps how many crafts fit in one window.
+            let crafts_per_limit = material_data
+                .iter()
+                .filter_map(|m| m.limit.map(|l| l / m.count.max(1) as i64))
+                .min();
+            let limit_profit = crafts_per_limit.map(|l| l * profit_after_tax);
+
+            // A recipe is only as fresh as the oldest price it is built from
+            let mut last_trade = pricing.sell.last_trade(gedata);
+            for m in &material_data {
+                last_trade = match (
+                    last_trade,
+                    ge.get(&m.id).and_then(|e| pricing.buy.last_trade(e)),
+                ) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    _ => None,
+                };
+            }
+
+            let ticks_per_action = c.ticks_per_action();
+            let actions_per_hour = ticks_per_action.map(|t| TICKS_PER_HOUR / t);
+            let skill_xp: Vec<SkillRate> = c
+                .skills
+                .iter()
+                .map(|s| SkillRate {
+                    skill: s.name.clone(),
+                    xp: s.xp(),
+                    xp_per_hour: actions_per_hour.map(|a| a * s.xp()),
+                })
+                .collect();
+            let total_xp: f64 = skill_xp.iter().map(|s| s.xp).sum();
+
             res.push(CraftingItemProfit {
                 name: c.name.clone(),
                 icon: c.icon.clone(),
@@ -619,10 +1418,26 @@ impl Osrs {
                 price,
                 profit_margin,
                 profit,
+                tax: item_tax,
+                profit_after_tax,
+                profit_margin_after_tax,
+                crafts_per_limit,
+                limit_profit,
+                volume: volume.get(&c.id).map(|v| v.total()),
+                last_trade,
+                ticks_per_action,
+                actions_per_hour,
+                gp_per_hour: actions_per_hour.map(|a| (a * profit_after_tax as f64).round() as i64),
+                skill_xp,
+                total_xp,
+                gp_per_xp: match total_xp > 0_f64 {
+                    true => Some(profit_after_tax as f64 / total_xp),
+                    false => None,
+                },
             })
         }
 
-        res.sort_by_key(|d| d.profit);
+        res.sort_by_key(|d| d.profit_after_tax);
         res.reverse();
 
         return res;
@@ -705,8 +1520,36 @@ pub struct CraftingSkill {
     pub boostable: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+/// Game ticks are 0.6 seconds.
+pub const TICKS_PER_HOUR: f64 = 6000_f64;
+
+/// Experience in one skill per craft and per hour of crafting.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillRate {
+    pub skill: String,
+    pub xp: f64,
+    pub xp_per_hour: Option<f64>,
+}
+
+impl CraftingSkill {
+    /// Experience per craft, zero when the wiki lists none.
+    pub fn xp(&self) -> f64 {
+        return self
+            .experience
+            .trim()
+            .replace(',', "")
+            .parse()
+            .unwrap_or(0_f64);
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CraftingItem {
+    /// Identifies the recipe across refreshes, see `Osrs::assign_recipe_keys`.
+    #[serde(skip)]
+    pub key: String,
     pub name: String,
     pub icon: String,
     pub id: i64,
@@ -718,15 +1561,47 @@ pub struct CraftingItem {
     pub output: u16,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CraftingMaterial {
     pub name: String,
     pub icon: String,
     pub id: i64,
     pub count: u8,
+    /// Taken from the current mappings rather than stored with the recipe.
+    #[serde(skip)]
+    pub limit: Option<i64>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+impl CraftingItem {
+    /// Game ticks per craft, None when the wiki gives no number (blank, "varies", ...).
+    pub fn ticks_per_action(&self) -> Option<f64> {
+        return match self.ticks.trim().parse::<f64>() {
+            Ok(e) if e > 0_f64 => Some(e),
+            _ => None,
+        };
+    }
+
+    /// Whether two recipes produce the same thing the same way, ignoring the buy limits
+    /// filled in from the mappings.
+    pub fn same_recipe(&self, other: &CraftingItem) -> bool {
+        return serde_json::to_value(self).ok() == serde_json::to_value(other).ok();
+    }
+}
+
+/// A recipe added, removed or changed by a recipe refresh.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeChange {
+    pub created: NaiveDateTime,
+    pub key: String,
+    pub change: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CraftingItemProfit {
     pub name: String,
     pub icon: String,
@@ -741,15 +1616,64 @@ pub struct CraftingItemProfit {
     pub price: i64,
     pub profit: i64,
     pub profit_margin: f32,
+    pub tax: i64,
+    pub profit_after_tax: i64,
+    pub profit_margin_after_tax: f32,
+    pub crafts_per_limit: Option<i64>,
+    pub limit_profit: Option<i64>,
+    pub volume: Option<i64>,
+    pub last_trade: Option<i64>,
+    pub ticks_per_action: Option<f64>,
+    pub actions_per_hour: Option<f64>,
+    /// `profit_after_tax` over an hour of crafting.
+    pub gp_per_hour: Option<i64>,
+    pub skill_xp: Vec<SkillRate>,
+    pub total_xp: f64,
+    /// `profit_after_tax` per point of `total_xp`, negative when training costs money.
+    pub gp_per_xp: Option<f64>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+impl CraftingItemProfit {
+    pub fn skill_names(&self) -> Vec<&str> {
+        return self.skill_xp.iter().map(|d| d.skill.as_str()).collect();
+    }
+
+    /// XP per hour in `skill`, or in every skill together.
+    pub fn xp_per_hour(&self, skill: Option<&str>) -> Option<f64> {
+        let xp = self.xp_in(skill)?;
+        return self.actions_per_hour.map(|a| a * xp);
+    }
+
+    /// Profit per XP in `skill`, or per XP across every skill.
+    pub fn gp_per_xp(&self, skill: Option<&str>) -> Option<f64> {
+        let xp = self.xp_in(skill)?;
+        if xp <= 0_f64 {
+            return None;
+        }
+        return Some(self.profit_after_tax as f64 / xp);
+    }
+
+    fn xp_in(&self, skill: Option<&str>) -> Option<f64> {
+        return match skill {
+            Some(s) => self
+                .skill_xp
+                .iter()
+                .find(|d| d.skill.eq_ignore_ascii_case(s))
+                .map(|d| d.xp),
+            None => Some(self.total_xp),
+        };
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CraftingMaterialCost {
     pub name: String,
     pub icon: String,
     pub id: i64,
     pub count: u8,
     pub cost: i64,
+    pub limit: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -782,6 +1706,242 @@ pub struct GePrice {
     pub low_time: Option<i64>,
 }
 
+impl GePrice {
+    /// Unix time of the older of the two sides' latest trades, as anything worked out from
+    /// both prices is only as recent as that.
+    pub fn last_trade(&self) -> Option<i64> {
+        match (self.high_time, self.low_time) {
+            (Some(h), Some(l)) => Some(h.min(l)),
+            (h, l) => h.or(l),
+        }
+    }
+}
+
+/// Averaging window of the wiki `/5m` and `/1h` endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VolumeTimestep {
+    #[serde(rename = "5m")]
+    FiveMinute,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+impl VolumeTimestep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VolumeTimestep::FiveMinute => "5m",
+            VolumeTimestep::OneHour => "1h",
+        }
+    }
+
+    /// Table the buckets for this timestep are stored in.
+    pub fn table(&self) -> &'static str {
+        match self {
+            VolumeTimestep::FiveMinute => "ge.volume_5m",
+            VolumeTimestep::OneHour => "ge.volume_1h",
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsrsVolumeData {
+    data: HashMap<String, GeVolume>,
+    timestamp: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeVolume {
+    pub avg_high_price: Option<i64>,
+    pub high_price_volume: i64,
+    pub avg_low_price: Option<i64>,
+    pub low_price_volume: i64,
+}
+
+impl GeVolume {
+    /// Items traded in the window on both sides of the book.
+    pub fn total(&self) -> i64 {
+        self.high_price_volume + self.low_price_volume
+    }
+}
+
+/// Bucket sizes offered by the wiki `/timeseries` endpoint that have somewhere to be stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeseriesTimestep {
+    #[serde(rename = "5m")]
+    FiveMinute,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "24h")]
+    OneDay,
+}
+
+impl TimeseriesTimestep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeseriesTimestep::FiveMinute => "5m",
+            TimeseriesTimestep::OneHour => "1h",
+            TimeseriesTimestep::OneDay => "24h",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "5m" => Some(TimeseriesTimestep::FiveMinute),
+            "1h" => Some(TimeseriesTimestep::OneHour),
+            "24h" => Some(TimeseriesTimestep::OneDay),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsrsTimeseries {
+    pub data: Vec<TimeseriesPoint>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeseriesPoint {
+    pub timestamp: i64,
+    pub avg_high_price: Option<i64>,
+    pub avg_low_price: Option<i64>,
+    #[serde(default)]
+    pub high_price_volume: i64,
+    #[serde(default)]
+    pub low_price_volume: i64,
+}
+
+/// A mapping field that changed between two wiki fetches, values as text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemChange {
+    pub created: NaiveDateTime,
+    pub item: i64,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GePriceHistory {
+    pub created: NaiveDateTime,
+    pub high: Option<i64>,
+    pub high_time: Option<i64>,
+    pub low: Option<i64>,
+    pub low_time: Option<i64>,
+}
+
+/// Granularity of stored price history.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Resolution {
+    #[serde(rename = "raw")]
+    Raw,
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "1d")]
+    Day,
+}
+
+impl Resolution {
+    /// Raw rows for up to two days, hourly buckets up to a month and daily beyond that, so a
+    /// range never returns more than a few hundred points per item.
+    pub fn for_range(since: Option<NaiveDateTime>) -> Self {
+        let since = match since {
+            Some(e) => e,
+            None => return Resolution::Day,
+        };
+
+        let range = Utc::now().naive_utc() - since;
+
+        if range <= chrono::Duration::days(2) {
+            Resolution::Raw
+        } else if range <= chrono::Duration::days(31) {
+            Resolution::Hour
+        } else {
+            Resolution::Day
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Raw => "raw",
+            Resolution::Hour => "1h",
+            Resolution::Day => "1d",
+        }
+    }
+}
+
+/// Open/high/low/close of the high and low prices over one bucket. Raw rows are a bucket of
+/// their own with a single sample.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GePriceCandle {
+    pub bucket: NaiveDateTime,
+    pub high_open: Option<i64>,
+    pub high_high: Option<i64>,
+    pub high_low: Option<i64>,
+    pub high_close: Option<i64>,
+    pub low_open: Option<i64>,
+    pub low_high: Option<i64>,
+    pub low_low: Option<i64>,
+    pub low_close: Option<i64>,
+    pub samples: i64,
+}
+
+impl From<GePriceHistory> for GePriceCandle {
+    fn from(e: GePriceHistory) -> Self {
+        GePriceCandle {
+            bucket: e.created,
+            high_open: e.high,
+            high_high: e.high,
+            high_low: e.high,
+            high_close: e.high,
+            low_open: e.low,
+            low_high: e.low,
+            low_low: e.low,
+            low_close: e.low,
+            samples: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceHistory {
+    pub resolution: Resolution,
+    pub candles: Vec<GePriceCandle>,
+}
+
+/// One item's flip, with `high` and `low` exactly as the wiki reported them.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlipProfit {
+    pub name: String,
+    pub id: i64,
+    pub icon: String,
+    pub members: bool,
+    pub high: i64,
+    pub low: i64,
+    pub high_time: Option<i64>,
+    pub low_time: Option<i64>,
+    pub spread: i64,
+    /// Tax on selling at `high`.
+    pub tax: i64,
+    /// `spread` less `tax`.
+    pub margin: i64,
+    /// `margin` as a percentage of `low`.
+    pub roi: f64,
+    pub inverted: bool,
+    pub limit: Option<i64>,
+    pub limit_profit: Option<i64>,
+    pub volume: Option<i64>,
+    pub last_trade: Option<i64>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HighAlchProfit {
@@ -793,6 +1953,10 @@ pub struct HighAlchProfit {
     pub ge_val: i64,
     pub profit_percent: i64,
     pub profit_per_use: i64,
+    pub limit: Option<i64>,
+    pub limit_profit: Option<i64>,
+    pub volume: Option<i64>,
+    pub last_trade: Option<i64>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -806,4 +1970,189 @@ pub struct LowAlchProfit {
     pub ge_val: i64,
     pub profit_percent: i64,
     pub profit_per_use: i64,
+    pub limit: Option<i64>,
+    pub limit_profit: Option<i64>,
+    pub volume: Option<i64>,
+    pub last_trade: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::data::pricing::PriceBasis;
+    use crate::repo::data::spell::NATURE_RUNE;
+
+    fn item(id: i64, name: &str, limit: Option<i64>) -> OsrsMap {
+        OsrsMap {
+            examine: String::new(),
+            id,
+            members: true,
+            lowalch: None,
+            limit,
+            value: 0,
+            highalch: None,
+            icon: String::new(),
+            name: name.to_string(),
+        }
+    }
+
+    fn price(high: Option<i64>, high_time: i64, low: Option<i64>, low_time: i64) -> GePrice {
+        GePrice {
+            high,
+            high_time: Some(high_time),
+            low,
+            low_time: Some(low_time),
+        }
+    }
+
+    #[test]
+    fn gen_flipping_takes_tax_off_the_high_side() {
+        let map = HashMap::from([(1, item(1, "Whip", Some(70)))]);
+        let ge = HashMap::from([(1, price(Some(1_500_000), 200, Some(1_400_000), 100))]);
+        let volume = HashMap::from([(
+            1,
+            GeVolume {
+                avg_high_price: None,
+                high_price_volume: 40,
+                avg_low_price: None,
+                low_price_volume: 60,
+            },
+        )]);
+
+        let flips = Osrs::gen_flipping(&ge, &map, &volume, &GeTax::default());
+
+        assert_eq!(flips.len(), 1);
+        let flip = &flips[0];
+        assert_eq!(flip.spread, 100_000);
+        assert_eq!(flip.tax, 30_000);
+        assert_eq!(flip.margin, 70_000);
+        assert_eq!(flip.roi, 5_f64);
+        assert!(!flip.inverted);
+        assert_eq!(flip.limit_profit, Some(4_900_000));
+        assert_eq!(flip.volume, Some(100));
+        assert_eq!(flip.last_trade, Some(100));
+    }
+
+    #[test]
+    fn gen_flipping_keeps_inverted_prices_and_sorts_by_margin() {
+        let map = HashMap::from([
+            (1, item(1, "Inverted", None)),
+            (2, item(2, "Wide", None)),
+            (3, item(3, "One sided", None)),
+        ]);
+        let ge = HashMap::from([
+            (1, price(Some(900), 10, Some(1_000), 20)),
+            (2, price(Some(2_000), 10, Some(1_000), 20)),
+            (3, price(Some(2_000), 10, None, 20)),
+            (4, price(Some(2_000), 10, Some(1_000), 20)),
+        ]);
+
+        let flips = Osrs::gen_flipping(&ge, &map, &HashMap::new(), &GeTax::default());
+
+        let names: Vec<&str> = flips.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["Wide", "Inverted"]);
+        assert!(flips[1].inverted);
+        assert_eq!(flips[1].margin, -118);
+        assert_eq!(flips[1].limit_profit, None);
+        assert_eq!(flips[1].volume, None);
+    }
+
+    #[test]
+    fn alch_rows_are_as_recent_as_the_price_they_buy_at() {
+        let map = HashMap::from([(
+            1,
+            OsrsMap {
+                highalch: Some(1_000),
+                ..item(1, "Rune dagger", None)
+            },
+        )]);
+        let ge = HashMap::from([
+            (1, price(Some(500), 300, Some(490), 100)),
+            (NATURE_RUNE, price(Some(100), 300, Some(99), 300)),
+        ]);
+
+        let rows = Osrs::gen_high_alch_profit(
+            &ge,
+            &map,
+            &HashMap::new(),
+            &Pricing::default(),
+            &RuneSetup::default(),
+        )
+        .unwrap();
+        assert_eq!(rows[0].last_trade, Some(300));
+
+        let low = Pricing {
+            buy: PriceBasis::Low,
+            ..Pricing::default()
+        };
+        let rows =
+            Osrs::gen_high_alch_profit(&ge, &map, &HashMap::new(), &low, &RuneSetup::default())
+                .unwrap();
+        assert_eq!(rows[0].last_trade, Some(100));
+    }
+
+    fn recipe(name: &str, ticks: &str, level: &str) -> CraftingItem {
+        CraftingItem {
+            name: name.to_string(),
+            id: 1,
+            ticks: ticks.to_string(),
+            materials: vec![CraftingMaterial {
+                id: 2,
+                count: 1,
+                ..CraftingMaterial::default()
+            }],
+            skills: vec![CraftingSkill {
+                experience: "10".to_string(),
+                level: level.to_string(),
+                name: "Smithing".to_string(),
+                boostable: "Yes".to_string(),
+            }],
+            output: 1,
+            ..CraftingItem::default()
+        }
+    }
+
+    fn keys(recipes: &mut [CraftingItem]) -> Vec<(String, String)> {
+        Osrs::assign_recipe_keys(recipes);
+        let mut keys: Vec<(String, String)> = recipes
+            .iter()
+            .map(|d| (d.name.clone(), d.key.clone()))
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn recipe_keys_only_grow_for_duplicates() {
+        let mut recipes = vec![recipe("Bar", "4", "1")];
+
+        assert_eq!(keys(&mut recipes), vec![("Bar".into(), "1:1x2:".into())]);
+    }
+
+    #[test]
+    fn recipe_keys_tell_duplicates_apart_by_requirements() {
+        let mut recipes = vec![recipe("Bar", "4", "1"), recipe("Bar", "4", "30")];
+        let before = keys(&mut recipes);
+        assert_eq!(before[0].1, "1:1x2::1x:Smithing 1");
+        assert_eq!(before[1].1, "1:1x2::1x:Smithing 30");
+
+        // Editing anything else keeps each recipe's key
+        let mut recipes = vec![recipe("Bar", "4", "30"), recipe("Bar", "9", "1")];
+        let after = keys(&mut recipes);
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn recipe_keys_number_identical_requirements_by_name() {
+        let mut recipes = vec![recipe("Bar (b)", "4", "1"), recipe("Bar (a)", "9", "1")];
+        let before = keys(&mut recipes);
+        assert_eq!(before[0], ("Bar (a)".into(), "1:1x2::1x:Smithing 1".into()));
+        assert_eq!(
+            before[1],
+            ("Bar (b)".into(), "1:1x2::1x:Smithing 1#2".into())
+        );
+
+        let mut recipes = vec![recipe("Bar (a)", "1", "1"), recipe("Bar (b)", "9", "1")];
+        assert_eq!(keys(&mut recipes), before);
+    }
 }
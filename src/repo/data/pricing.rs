@@ -0,0 +1,205 @@
+use crate::repo::data::osrs::{GePrice, GeVolume};
+
+use serde::{Deserialize, Serialize};
+
+/// Which GE price an item is valued at. `High` is what an instant buy costs and `Low` what
+/// an instant sell gets, so buying at `High` and selling at `Low` is the pessimistic end.
+/// Any basis falls back to whichever side is known when its own price is missing.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceBasis {
+    #[default]
+    High,
+    Low,
+    Mid,
+    /// Average of both sides over the last hour, weighted by how much traded on each.
+    #[serde(rename = "vwap_1h")]
+    Vwap1h,
+}
+
+pub const PRICE_BASES: [PriceBasis; 4] = [
+    PriceBasis::High,
+    PriceBasis::Low,
+    PriceBasis::Mid,
+    PriceBasis::Vwap1h,
+];
+
+impl PriceBasis {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceBasis::High => "high",
+            PriceBasis::Low => "low",
+            PriceBasis::Mid => "mid",
+            PriceBasis::Vwap1h => "vwap_1h",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PriceBasis::High => "Instant-buy (high)",
+            PriceBasis::Low => "Instant-sell (low)",
+            PriceBasis::Mid => "Mid price",
+            PriceBasis::Vwap1h => "1h volume-weighted average",
+        }
+    }
+
+    /// `volume` is the item's 1h volume, only used by `Vwap1h`, which is the mid price when
+    /// nothing traded in the last hour.
+    pub fn price(&self, ge: &GePrice, volume: Option<&GeVolume>) -> Option<i64> {
+        match self {
+            PriceBasis::High => ge.high.or(ge.low),
+            PriceBasis::Low => ge.low.or(ge.high),
+            PriceBasis::Mid => match (ge.high, ge.low) {
+                (Some(h), Some(l)) => Some((h + l) / 2),
+                (h, l) => h.or(l),
+            },
+            PriceBasis::Vwap1h => {
+                let sides = match volume {
+                    Some(v) => [
+                        (v.avg_high_price, v.high_price_volume),
+                        (v.avg_low_price, v.low_price_volume),
+                    ],
+                    None => return PriceBasis::Mid.price(ge, None),
+                };
+
+                let mut value = 0_f64;
+                let mut traded = 0_i64;
+                for (price, count) in sides {
+                    if let Some(p) = price {
+                        if count > 0 {
+                            value += p as f64 * count as f64;
+                            traded += count;
+                        }
+                    }
+                }
+
+                match traded > 0 {
+                    true => Some((value / traded as f64).round() as i64),
+                    false => PriceBasis::Mid.price(ge, None),
+                }
+            }
+        }
+    }
+
+    /// Unix time of the trade behind `price`, falling back the same way. Prices built from
+    /// both sides are only as recent as the older one.
+    pub fn last_trade(&self, ge: &GePrice) -> Option<i64> {
+        match self {
+            PriceBasis::High => match ge.high {
+                Some(_) => ge.high_time,
+                None => ge.low_time,
+            },
+            PriceBasis::Low => match ge.low {
+                Some(_) => ge.low_time,
+                None => ge.high_time,
+            },
+            PriceBasis::Mid | PriceBasis::Vwap1h => ge.last_trade(),
+        }
+    }
+}
+
+/// Prices used to buy inputs and to value what is sold. The default buys and sells at `High`,
+/// which is what every table used before the basis was selectable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pricing {
+    pub buy: PriceBasis,
+    pub sell: PriceBasis,
+}
+
+impl Pricing {
+    pub fn is_default(&self) -> bool {
+        *self == Pricing::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ge(high: Option<i64>, low: Option<i64>) -> GePrice {
+        GePrice {
+            high,
+            low,
+            ..GePrice::default()
+        }
+    }
+
+    fn volume(high: (Option<i64>, i64), low: (Option<i64>, i64)) -> GeVolume {
+        GeVolume {
+            avg_high_price: high.0,
+            high_price_volume: high.1,
+            avg_low_price: low.0,
+            low_price_volume: low.1,
+        }
+    }
+
+    #[test]
+    fn picks_the_basis_side() {
+        let both = ge(Some(110), Some(101));
+
+        assert_eq!(PriceBasis::High.price(&both, None), Some(110));
+        assert_eq!(PriceBasis::Low.price(&both, None), Some(101));
+        assert_eq!(PriceBasis::Mid.price(&both, None), Some(105));
+    }
+
+    #[test]
+    fn falls_back_to_the_known_side() {
+        let high_only = ge(Some(110), None);
+        let low_only = ge(None, Some(101));
+
+        assert_eq!(PriceBasis::Low.price(&high_only, None), Some(110));
+        assert_eq!(PriceBasis::Mid.price(&high_only, None), Some(110));
+        assert_eq!(PriceBasis::High.price(&low_only, None), Some(101));
+        assert_eq!(PriceBasis::Vwap1h.price(&low_only, None), Some(101));
+        assert_eq!(PriceBasis::Mid.price(&ge(None, None), None), None);
+    }
+
+    #[test]
+    fn vwap_weights_each_side_by_volume() {
+        let both = ge(Some(110), Some(101));
+        let v = volume((Some(120), 30), (Some(100), 10));
+
+        assert_eq!(PriceBasis::Vwap1h.price(&both, Some(&v)), Some(115));
+        assert_eq!(PriceBasis::High.price(&both, Some(&v)), Some(110));
+    }
+
+    #[test]
+    fn vwap_skips_sides_without_trades() {
+        let both = ge(Some(110), Some(101));
+        let v = volume((Some(120), 0), (Some(100), 10));
+
+        assert_eq!(PriceBasis::Vwap1h.price(&both, Some(&v)), Some(100));
+    }
+
+    #[test]
+    fn last_trade_follows_the_priced_side() {
+        let both = GePrice {
+            high: Some(110),
+            high_time: Some(2_000),
+            low: Some(101),
+            low_time: Some(1_000),
+        };
+
+        assert_eq!(PriceBasis::High.last_trade(&both), Some(2_000));
+        assert_eq!(PriceBasis::Low.last_trade(&both), Some(1_000));
+        assert_eq!(PriceBasis::Mid.last_trade(&both), Some(1_000));
+        assert_eq!(PriceBasis::Vwap1h.last_trade(&both), Some(1_000));
+
+        let high_only = GePrice {
+            low: None,
+            low_time: None,
+            ..both
+        };
+        assert_eq!(PriceBasis::Low.last_trade(&high_only), Some(2_000));
+        assert_eq!(PriceBasis::Mid.last_trade(&high_only), Some(2_000));
+    }
+
+    #[test]
+    fn vwap_falls_back_to_mid_without_volume() {
+        let both = ge(Some(110), Some(101));
+
+        assert_eq!(PriceBasis::Vwap1h.price(&both, None), Some(105));
+        let quiet = volume((Some(120), 0), (None, 25));
+        assert_eq!(PriceBasis::Vwap1h.price(&both, Some(&quiet)), Some(105));
+    }
+}
@@ -0,0 +1,273 @@
+use crate::repo::data::osrs::{CraftingItemProfit, CraftingSkill};
+
+use std::collections::BTreeMap;
+
+use axum::http::header::COOKIE;
+use axum::http::HeaderMap;
+use reqwest::Url;
+use serde::Serialize;
+
+const COOKIE_NAME: &str = "profile";
+/// A year, so the profile outlives browser restarts.
+const COOKIE_MAX_AGE: i64 = 31_536_000;
+pub const MAX_LEVEL: u8 = 99;
+/// Spicy stews can boost most skills by up to 6, nothing common goes further.
+pub const MAX_BOOST: u8 = 6;
+
+/// The player's skill levels, kept in a cookie so recipes they cannot make yet can be hidden.
+/// Skills without a level are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub levels: BTreeMap<String, u8>,
+    /// Levels a boostable requirement may be above the player's level.
+    pub boost: u8,
+    pub hide_members: bool,
+}
+
+impl Profile {
+    /// Reads the profile cookie, ignoring anything in it that doesn't parse.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let value = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|h| h.split(';'))
+            .filter_map(|c| c.trim().split_once('='))
+            .find(|(name, _)| *name == COOKIE_NAME)
+            .map(|(_, value)| value.to_string());
+
+        let value = match value {
+            Some(e) => e,
+            None => return Profile::default(),
+        };
+
+        let pairs: Vec<(String, String)> = match Url::parse(&format!("http://localhost/?{}", value))
+        {
+            Ok(e) => e.query_pairs().into_owned().collect(),
+            Err(_) => return Profile::default(),
+        };
+
+        let mut profile = Profile::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "boost" => profile.boost = value.parse().unwrap_or(0).min(MAX_BOOST),
+                "hide_members" => profile.hide_members = value == "1",
+                _ => {
+                    if let Ok(e) = value.parse::<u8>() {
+                        if (1..=MAX_LEVEL).contains(&e) {
+                            profile.levels.insert(key, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        return profile;
+    }
+
+    /// Builds a profile from the submitted form, where skill levels are named `level_<skill>`
+    /// and left empty for skills that shouldn't be checked.
+    pub fn from_form(form: &[(String, String)]) -> Result<Self, String> {
+        let mut profile = Profile::default();
+
+        for (key, value) in form {
+            let value = value.trim();
+            if key == "hide_members" {
+                profile.hide_members = !value.is_empty();
+                continue;
+            }
+            if value.is_empty() {
+                continue;
+            }
+
+            if key == "boost" {
+                profile.boost = match value.parse::<u8>() {
+                    Ok(e) if e <= MAX_BOOST => e,
+                    _ => return Err(format!("Boost must be between 0 and {}", MAX_BOOST)),
+                };
+            } else if let Some(skill) = key.strip_prefix("level_") {
+                match value.parse::<u8>() {
+                    Ok(e) if (1..=MAX_LEVEL).contains(&e) => {
+                        profile.levels.insert(skill.to_string(), e);
+                    }
+                    _ => {
+                        return Err(format!(
+                            "{} level must be between 1 and {}",
+                            skill, MAX_LEVEL
+                        ))
+                    }
+                };
+            }
+        }
+
+        return Ok(profile);
+    }
+
+    /// `Set-Cookie` value storing this profile, or clearing it when there is nothing to keep.
+    pub fn to_cookie(&self) -> String {
+        if self.is_empty() {
+            return format!("{}=; Path=/; Max-Age=0; SameSite=Lax", COOKIE_NAME);
+        }
+
+        let mut url = Url::parse("http://localhost/").unwrap();
+        {
+            let mut q = url.query_pairs_mut();
+            q.append_pair("boost", &self.boost.to_string());
+            if self.hide_members {
+                q.append_pair("hide_members", "1");
+            }
+            for (skill, level) in &self.levels {
+                q.append_pair(skill, &level.to_string());
+            }
+        }
+
+        format!(
+            "{}={}; Path=/; Max-Age={}; SameSite=Lax; HttpOnly",
+            COOKIE_NAME,
+            url.query().unwrap_or_default(),
+            COOKIE_MAX_AGE
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty() && !self.hide_members
+    }
+
+    pub fn level(&self, skill: &str) -> Option<u8> {
+        self.levels
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(skill))
+            .map(|(_, level)| *level)
+    }
+
+    /// Whether every skill requirement is met, counting the boost for boostable ones.
+    /// Requirements the wiki gives no number for are assumed to be met.
+    pub fn meets(&self, skills: &[CraftingSkill]) -> bool {
+        skills.iter().all(|s| {
+            let level = match self.level(&s.name) {
+                Some(e) => e as i64,
+                None => return true,
+            };
+            let required: i64 = match s.level.trim().parse() {
+                Ok(e) => e,
+                Err(_) => return true,
+            };
+            let boost = match s.boostable.trim().eq_ignore_ascii_case("yes") {
+                true => self.boost as i64,
+                false => 0,
+            };
+
+            required <= level + boost
+        })
+    }
+
+    pub fn allows(&self, recipe: &CraftingItemProfit) -> bool {
+        if self.hide_members && recipe.members == "Yes" {
+            return false;
+        }
+
+        return self.meets(&recipe.skills);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(cookie: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_str(cookie).unwrap());
+        headers
+    }
+
+    fn skill(name: &str, level: &str, boostable: &str) -> CraftingSkill {
+        CraftingSkill {
+            experience: "10".to_string(),
+            level: level.to_string(),
+            name: name.to_string(),
+            boostable: boostable.to_string(),
+        }
+    }
+
+    #[test]
+    fn cookie_round_trips() {
+        let profile = Profile {
+            levels: BTreeMap::from([("Crafting".to_string(), 40), ("Smithing".to_string(), 99)]),
+            boost: 3,
+            hide_members: true,
+        };
+
+        let cookie = profile.to_cookie();
+        let value = cookie.split(';').next().unwrap();
+
+        assert_eq!(Profile::from_headers(&headers(value)), profile);
+        assert_eq!(
+            Profile::from_headers(&headers(&format!("theme=dark; {}", value))),
+            profile
+        );
+    }
+
+    #[test]
+    fn empty_profile_clears_cookie() {
+        assert!(Profile::default().to_cookie().contains("Max-Age=0"));
+        assert_eq!(Profile::from_headers(&HeaderMap::new()), Profile::default());
+    }
+
+    #[test]
+    fn ignores_bad_cookie_values() {
+        let profile = Profile::from_headers(&headers(
+            "profile=boost=abc&hide_members=yes&Smithing=150&Mining=0&Fishing=x&Crafting=40",
+        ));
+
+        assert_eq!(profile.boost, 0);
+        assert!(!profile.hide_members);
+        assert_eq!(
+            profile.levels,
+            BTreeMap::from([("Crafting".to_string(), 40)])
+        );
+
+        let profile = Profile::from_headers(&headers("profile=boost=200"));
+        assert_eq!(profile.boost, MAX_BOOST);
+    }
+
+    #[test]
+    fn meets_counts_boost() {
+        let mut profile = Profile {
+            levels: BTreeMap::from([("Smithing".to_string(), 14)]),
+            ..Profile::default()
+        };
+        let skills = [skill("Smithing", "15", "Yes")];
+
+        assert!(!profile.meets(&skills));
+        profile.boost = 1;
+        assert!(profile.meets(&skills));
+        assert!(!profile.meets(&[skill("Smithing", "16", "Yes")]));
+    }
+
+    #[test]
+    fn boost_only_applies_to_boostable_requirements() {
+        let profile = Profile {
+            levels: BTreeMap::from([("Smithing".to_string(), 14)]),
+            boost: 5,
+            hide_members: false,
+        };
+
+        assert!(!profile.meets(&[skill("Smithing", "15", "No")]));
+        assert!(!profile.meets(&[skill("Smithing", "15", "")]));
+        assert!(profile.meets(&[skill("Smithing", "14", "No")]));
+    }
+
+    #[test]
+    fn meets_skips_unknown_skills_and_levels() {
+        let profile = Profile {
+            levels: BTreeMap::from([("smithing".to_string(), 1)]),
+            ..Profile::default()
+        };
+
+        assert!(profile.meets(&[skill("Crafting", "99", "No")]));
+        assert!(profile.meets(&[skill("Smithing", "varies", "No")]));
+        assert!(!profile.meets(&[skill("Smithing", "2", "No")]));
+    }
+}
@@ -0,0 +1,793 @@
+use crate::repo::data::error::OsrsError;
+use crate::repo::data::osrs::{
+    CraftingItem, GePrice, GeVolume, OsrsMap, SkillRate, TICKS_PER_HOUR,
+};
+use crate::repo::data::pricing::{PriceBasis, Pricing};
+use crate::repo::data::tax::GeTax;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+pub const NATURE_RUNE: i64 = 561;
+pub const FIRE_RUNE: i64 = 554;
+const WATER_RUNE: i64 = 555;
+const AIR_RUNE: i64 = 556;
+const EARTH_RUNE: i64 = 557;
+const MIND_RUNE: i64 = 558;
+const COSMIC_RUNE: i64 = 564;
+const ASTRAL_RUNE: i64 = 9075;
+
+/// `(rune, count)` for one cast of High and Low Level Alchemy.
+pub const HIGH_ALCH_RUNES: [(i64, i64); 2] = [(NATURE_RUNE, 1), (FIRE_RUNE, 5)];
+pub const LOW_ALCH_RUNES: [(i64, i64); 2] = [(NATURE_RUNE, 1), (FIRE_RUNE, 3)];
+
+/// Bryophyta's staff saves the nature rune on one cast in 15.
+const BRYOPHYTA_NATURE_COST: f64 = 14_f64 / 15_f64;
+
+/// The staff held while casting.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Staff {
+    /// Every rune is paid for.
+    None,
+    /// Any staff that provides fire runes. On the spells table this stands for whichever
+    /// elemental staff suits the spell, see `Spell::staff`.
+    #[default]
+    Fire,
+    /// Saves nature runes, but fire runes are paid for unless a tome of fire is held.
+    Bryophyta,
+    Air,
+    Water,
+    Earth,
+    /// Provides both water and earth runes.
+    Mud,
+}
+
+pub const STAVES: [Staff; 3] = [Staff::None, Staff::Fire, Staff::Bryophyta];
+
+impl Staff {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Staff::None => "none",
+            Staff::Fire => "fire",
+            Staff::Bryophyta => "bryophyta",
+            Staff::Air => "air",
+            Staff::Water => "water",
+            Staff::Earth => "earth",
+            Staff::Mud => "mud",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Staff::None => "No staff",
+            Staff::Fire => "Fire staff",
+            Staff::Bryophyta => "Bryophyta's staff",
+            Staff::Air => "Air staff",
+            Staff::Water => "Water staff",
+            Staff::Earth => "Earth staff",
+            Staff::Mud => "Mud battlestaff",
+        }
+    }
+
+    /// Whether casting with this staff needs none of `rune`.
+    pub fn provides(&self, rune: i64) -> bool {
+        match self {
+            Staff::None | Staff::Bryophyta => false,
+            Staff::Fire => rune == FIRE_RUNE,
+            Staff::Air => rune == AIR_RUNE,
+            Staff::Water => rune == WATER_RUNE,
+            Staff::Earth => rune == EARTH_RUNE,
+            Staff::Mud => rune == WATER_RUNE || rune == EARTH_RUNE,
+        }
+    }
+}
+
+/// How a player pays for the runes of a cast. The default, a fire staff with nature runes
+/// bought at `high`, is what the alch tables always assumed. Runes the staff provides are
+/// free, as are fire runes with a tome of fire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RuneSetup {
+    pub staff: Staff,
+    pub tome_of_fire: bool,
+    /// Price runes are bought at.
+    pub runes: PriceBasis,
+    /// Runes already in stock are valued at nothing.
+    pub free_runes: bool,
+}
+
+impl RuneSetup {
+    pub fn is_default(&self) -> bool {
+        *self == RuneSetup::default()
+    }
+
+    fn provides(&self, rune: i64) -> bool {
+        self.staff.provides(rune) || (rune == FIRE_RUNE && self.tome_of_fire)
+    }
+
+    /// The setup as used for `spell`, holding the spell's own elemental staff in place of
+    /// the default fire staff.
+    pub fn for_spell(&self, spell: &Spell) -> RuneSetup {
+        match self.staff {
+            Staff::Fire => RuneSetup {
+                staff: spell.staff,
+                ..*self
+            },
+            _ => *self,
+        }
+    }
+
+    /// Average cost of one cast needing `runes` as `(rune, count)`, rounded to whole gp.
+    pub fn cast_cost(
+        &self,
+        runes: &[(i64, i64)],
+        ge: &HashMap<i64, GePrice>,
+        volume: &HashMap<i64, GeVolume>,
+    ) -> Result<i64, OsrsError> {
+        if self.free_runes {
+            return Ok(0);
+        }
+
+        let mut cost = 0_f64;
+
+        for (id, count) in runes {
+            if self.provides(*id) {
+                continue;
+            }
+
+            let mut rune_cost = (self.rune_price(*id, ge, volume)? * count) as f64;
+            if *id == NATURE_RUNE && self.staff == Staff::Bryophyta {
+                rune_cost *= BRYOPHYTA_NATURE_COST;
+            }
+            cost += rune_cost;
+        }
+
+        return Ok(cost.round() as i64);
+    }
+
+    fn rune_price(
+        &self,
+        id: i64,
+        ge: &HashMap<i64, GePrice>,
+        volume: &HashMap<i64, GeVolume>,
+    ) -> Result<i64, OsrsError> {
+        match ge
+            .get(&id)
+            .and_then(|e| self.runes.price(e, volume.get(&id)))
+        {
+            Some(e) => Ok(e),
+            None => Err(OsrsError::MissingReferencePrice(id)),
+        }
+    }
+}
+
+/// Where the items a spell is cast on come from.
+enum SpellRecipes {
+    /// Production recipes made at this facility, which the spell does instead.
+    Facility(&'static str),
+    /// Furnace recipes that smelt ore into a bar.
+    Smelting,
+    /// Production recipes that string jewellery with a ball of wool, which the spell replaces.
+    Stringing,
+    /// `(input, output)` item names, for spells the production data has nothing on.
+    Pairs(&'static [(&'static str, &'static str)]),
+}
+
+/// A spell that turns bought items into something worth more, or into cheap XP.
+pub struct Spell {
+    pub name: &'static str,
+    pub level: u8,
+    /// Magic XP per cast.
+    pub xp: f64,
+    pub members: bool,
+    /// `(rune, count)` per cast.
+    pub runes: &'static [(i64, i64)],
+    /// Elemental staff that saves the most runes on this spell.
+    pub staff: Staff,
+    /// Items processed by one cast.
+    pub per_cast: i64,
+    /// Typical game ticks per cast when casting back to back.
+    pub ticks: f64,
+    /// Coins charged per item on top of the runes, by output name.
+    pub coins: &'static [(&'static str, i64)],
+    recipes: SpellRecipes,
+}
+
+pub const SPELLS: [Spell; 10] = [
+    Spell {
+        name: "Superheat Item",
+        level: 43,
+        xp: 53_f64,
+        members: true,
+        runes: &[(NATURE_RUNE, 1), (FIRE_RUNE, 4)],
+        staff: Staff::Fire,
+        per_cast: 1,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Smelting,
+    },
+    Spell {
+        name: "Plank Make",
+        level: 86,
+        xp: 90_f64,
+        members: true,
+        runes: &[(ASTRAL_RUNE, 2), (NATURE_RUNE, 1), (EARTH_RUNE, 15)],
+        staff: Staff::Earth,
+        per_cast: 1,
+        ticks: 3_f64,
+        coins: &[
+            ("Plank", 70),
+            ("Oak plank", 175),
+            ("Teak plank", 350),
+            ("Mahogany plank", 1050),
+        ],
+        recipes: SpellRecipes::Facility("Sawmill"),
+    },
+    Spell {
+        name: "Tan Leather",
+        level: 78,
+        xp: 81_f64,
+        members: true,
+        runes: &[(ASTRAL_RUNE, 2), (NATURE_RUNE, 1), (FIRE_RUNE, 5)],
+        staff: Staff::Fire,
+        per_cast: 5,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Facility("Tannery"),
+    },
+    Spell {
+        name: "String Jewellery",
+        level: 80,
+        xp: 83_f64,
+        members: true,
+        runes: &[(ASTRAL_RUNE, 2), (EARTH_RUNE, 10), (WATER_RUNE, 5)],
+        staff: Staff::Mud,
+        per_cast: 1,
+        ticks: 5_f64,
+        coins: &[],
+        recipes: SpellRecipes::Stringing,
+    },
+    Spell {
+        name: "Lvl-1 Enchant",
+        level: 7,
+        xp: 17.5,
+        members: false,
+        runes: &[(COSMIC_RUNE, 1), (WATER_RUNE, 1)],
+        staff: Staff::Water,
+        per_cast: 1,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Pairs(&[
+            ("Sapphire ring", "Ring of recoil"),
+            ("Sapphire amulet", "Amulet of magic"),
+        ]),
+    },
+    Spell {
+        name: "Lvl-2 Enchant",
+        level: 27,
+        xp: 37_f64,
+        members: false,
+        runes: &[(COSMIC_RUNE, 1), (AIR_RUNE, 3)],
+        staff: Staff::Air,
+        per_cast: 1,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Pairs(&[
+            ("Emerald ring", "Ring of dueling(8)"),
+            ("Emerald amulet", "Amulet of defence"),
+        ]),
+    },
+    Spell {
+        name: "Lvl-3 Enchant",
+        level: 49,
+        xp: 59_f64,
+        members: false,
+        runes: &[(COSMIC_RUNE, 1), (FIRE_RUNE, 5)],
+        staff: Staff::Fire,
+        per_cast: 1,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Pairs(&[
+            ("Ruby ring", "Ring of forging"),
+            ("Ruby amulet", "Amulet of strength"),
+        ]),
+    },
+    Spell {
+        name: "Lvl-4 Enchant",
+        level: 57,
+        xp: 67_f64,
+        members: false,
+        runes: &[(COSMIC_RUNE, 1), (EARTH_RUNE, 10)],
+        staff: Staff::Earth,
+        per_cast: 1,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Pairs(&[
+            ("Diamond ring", "Ring of life"),
+            ("Diamond amulet", "Amulet of power"),
+        ]),
+    },
+    Spell {
+        name: "Enchant Sapphire Bolt",
+        level: 7,
+        xp: 17.5,
+        members: true,
+        runes: &[(COSMIC_RUNE, 1), (WATER_RUNE, 1), (MIND_RUNE, 1)],
+        staff: Staff::Water,
+        per_cast: 10,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Pairs(&[("Sapphire bolts", "Sapphire bolts (e)")]),
+    },
+    Spell {
+        name: "Enchant Emerald Bolt",
+        level: 27,
+        xp: 37_f64,
+        members: true,
+        runes: &[(NATURE_RUNE, 1), (AIR_RUNE, 3)],
+        staff: Staff::Air,
+        per_cast: 10,
+        ticks: 3_f64,
+        coins: &[],
+        recipes: SpellRecipes::Pairs(&[("Emerald bolts", "Emerald bolts (e)")]),
+    },
+];
+
+/// One item a spell is cast on, with `count` used per cast.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellInput {
+    pub id: i64,
+    pub name: String,
+    pub icon: String,
+    pub count: i64,
+    /// Unit price at the buy basis.
+    pub cost: i64,
+    pub limit: Option<i64>,
+}
+
+/// What a spell is cast on and what comes out, before any prices are known.
+struct SpellRecipe {
+    spell: &'static Spell,
+    id: i64,
+    name: String,
+    icon: String,
+    members: bool,
+    /// `(id, name, icon, count per cast, buy limit)`.
+    inputs: Vec<(i64, String, String, i64, Option<i64>)>,
+    outputs: i64,
+    /// XP in skills other than Magic per cast, such as Smithing from Superheat Item.
+    xp: Vec<(String, f64)>,
+}
+
+/// Profit of one cast, with the output sold back to the GE.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellProfit {
+    pub spell: String,
+    pub level: u8,
+    pub name: String,
+    pub id: i64,
+    pub icon: String,
+    pub members: bool,
+    pub inputs: Vec<SpellInput>,
+    /// Staff the rune cost assumes.
+    pub staff: Staff,
+    /// Items made per cast.
+    pub outputs: i64,
+    pub price: i64,
+    pub input_cost: i64,
+    pub rune_cost: i64,
+    pub coin_cost: i64,
+    pub tax: i64,
+    /// Per cast, after tax.
+    pub profit: i64,
+    pub profit_margin: f64,
+    pub skill_xp: Vec<SkillRate>,
+    pub total_xp: f64,
+    pub casts_per_hour: f64,
+    pub gp_per_hour: i64,
+    pub casts_per_limit: Option<i64>,
+    pub limit_profit: Option<i64>,
+    pub volume: Option<i64>,
+    pub last_trade: Option<i64>,
+}
+
+impl SpellProfit {
+    pub fn skill_names(&self) -> Vec<&str> {
+        return self.skill_xp.iter().map(|d| d.skill.as_str()).collect();
+    }
+
+    fn xp_in(&self, skill: Option<&str>) -> Option<f64> {
+        return match skill {
+            Some(s) => self
+                .skill_xp
+                .iter()
+                .find(|d| d.skill.eq_ignore_ascii_case(s))
+                .map(|d| d.xp),
+            None => Some(self.total_xp),
+        };
+    }
+
+    /// XP per hour in `skill`, or in every skill together.
+    pub fn xp_per_hour(&self, skill: Option<&str>) -> Option<f64> {
+        return self.xp_in(skill).map(|xp| xp * self.casts_per_hour);
+    }
+
+    /// Profit per XP in `skill`, or per XP across every skill. Negative when training
+    /// costs money.
+    pub fn gp_per_xp(&self, skill: Option<&str>) -> Option<f64> {
+        let xp = self.xp_in(skill)?;
+        if xp <= 0_f64 {
+            return None;
+        }
+        return Some(self.profit as f64 / xp);
+    }
+}
+
+/// Every spell cast on every item it applies to, most profitable first. Casts whose items or
+/// runes have no price are left out.
+pub fn gen_spell_profit(
+    crafting: &[CraftingItem],
+    map: &HashMap<i64, OsrsMap>,
+    ge: &HashMap<i64, GePrice>,
+    volume: &HashMap<i64, GeVolume>,
+    tax: &GeTax,
+    pricing: &Pricing,
+    setup: &RuneSetup,
+) -> Vec<SpellProfit> {
+    let mut res: Vec<SpellProfit> = Vec::new();
+
+    for spell in SPELLS.iter() {
+        let setup = setup.for_spell(spell);
+        let rune_cost = match setup.cast_cost(spell.runes, ge, volume) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+
+        for recipe in spell_recipes(spell, crafting, map) {
+            match spell_profit(&recipe, setup.staff, rune_cost, ge, volume, tax, pricing) {
+                Some(e) => res.push(e),
+                None => continue,
+            };
+        }
+    }
+
+    res.sort_by_key(|d| d.profit);
+    res.reverse();
+
+    return res;
+}
+
+fn spell_recipes(
+    spell: &'static Spell,
+    crafting: &[CraftingItem],
+    map: &HashMap<i64, OsrsMap>,
+) -> Vec<SpellRecipe> {
+    let from_production = |c: &CraftingItem, skip: Option<&str>| SpellRecipe {
+        spell,
+        id: c.id,
+        name: c.name.clone(),
+        icon: c.icon.clone(),
+        members: c.members == "Yes",
+        inputs: c
+            .materials
+            .iter()
+            .filter(|m| skip.is_none_or(|s| m.name != s))
+            .map(|m| {
+                (
+                    m.id,
+                    m.name.clone(),
+                    m.icon.clone(),
+                    m.count as i64 * spell.per_cast,
+                    m.limit,
+                )
+            })
+            .collect(),
+        outputs: c.output as i64 * spell.per_cast,
+        xp: c
+            .skills
+            .iter()
+            .map(|s| (s.name.clone(), s.xp() * spell.per_cast as f64))
+            .filter(|(_, xp)| *xp > 0_f64)
+            .collect(),
+    };
+
+    let facility = |c: &CraftingItem, name: &str| {
+        c.facilities
+            .as_ref()
+            .is_some_and(|f| f.eq_ignore_ascii_case(name))
+    };
+
+    match spell.recipes {
+        SpellRecipes::Facility(name) => crafting
+            .iter()
+            .filter(|c| facility(c, name))
+            .map(|c| from_production(c, None))
+            .collect(),
+        SpellRecipes::Smelting => crafting
+            .iter()
+            .filter(|c| facility(c, "Furnace") && c.name.ends_with(" bar"))
+            .map(|c| from_production(c, None))
+            .collect(),
+        SpellRecipes::Stringing => crafting
+            .iter()
+            .filter(|c| c.materials.iter().any(|m| m.name == "Ball of wool"))
+            .map(|c| from_production(c, Some("Ball of wool")))
+            .collect(),
+        SpellRecipes::Pairs(pairs) => {
+            let by_name: HashMap<&str, &OsrsMap> =
+                map.values().map(|d| (d.name.as_str(), d)).collect();
+
+            pairs
+                .iter()
+                .filter_map(|(input, output)| {
+                    let input = by_name.get(input)?;
+                    let output = by_name.get(output)?;
+
+                    Some(SpellRecipe {
+                        spell,
+                        id: output.id,
+                        name: output.name.clone(),
+                        icon: output.icon.clone(),
+                        members: output.members,
+                        inputs: vec![(
+                            input.id,
+                            input.name.clone(),
+                            input.icon.clone(),
+                            spell.per_cast,
+                            input.limit,
+                        )],
+                        outputs: spell.per_cast,
+                        xp: Vec::new(),
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+fn spell_profit(
+    recipe: &SpellRecipe,
+    staff: Staff,
+    rune_cost: i64,
+    ge: &HashMap<i64, GePrice>,
+    volume: &HashMap<i64, GeVolume>,
+    tax: &GeTax,
+    pricing: &Pricing,
+) -> Option<SpellProfit> {
+    let spell = recipe.spell;
+    if recipe.inputs.is_empty() || recipe.outputs <= 0 {
+        return None;
+    }
+
+    let gedata = ge.get(&recipe.id)?;
+    let price = pricing.sell.price(gedata, volume.get(&recipe.id))?;
+
+    let mut inputs: Vec<SpellInput> = Vec::new();
+    for (id, name, icon, count, limit) in &recipe.inputs {
+        let cost = pricing.buy.price(ge.get(id)?, volume.get(id))?;
+        inputs.push(SpellInput {
+            id: *id,
+            name: name.clone(),
+            icon: icon.clone(),
+            count: *count,
+            cost,
+            limit: *limit,
+        });
+    }
+
+    let input_cost: i64 = inputs.iter().map(|m| m.cost * m.count).sum();
+    let coin_cost = spell
+        .coins
+        .iter()
+        .find(|(name, _)| *name == recipe.name)
+        .map(|(_, coins)| coins * recipe.outputs)
+        .unwrap_or(0);
+
+    let item_tax = tax.tax(&recipe.id, price) * recipe.outputs;
+    let profit =
+        tax.after_tax(&recipe.id, price) * recipe.outputs - input_cost - rune_cost - coin_cost;
+
+    let casts_per_hour = TICKS_PER_HOUR / spell.ticks;
+
+    let mut xp = vec![("Magic".to_string(), spell.xp)];
+    xp.extend(recipe.xp.iter().cloned());
+    let skill_xp: Vec<SkillRate> = xp
+        .into_iter()
+        .map(|(skill, xp)| SkillRate {
+            skill,
+            xp,
+            xp_per_hour: Some(xp * casts_per_hour),
+        })
+        .collect();
+    let total_xp: f64 = skill_xp.iter().map(|s| s.xp).sum();
+
+    // The input whose buy limit runs out first caps how many casts fit in one window
+    let casts_per_limit = inputs
+        .iter()
+        .filter_map(|m| m.limit.map(|l| l / m.count.max(1)))
+        .min();
+
+    // A cast is only as fresh as the oldest price it is built from
+    let mut last_trade = pricing.sell.last_trade(gedata);
+    for m in &inputs {
+        last_trade = match (
+            last_trade,
+            ge.get(&m.id).and_then(|e| pricing.buy.last_trade(e)),
+        ) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            _ => None,
+        };
+    }
+
+    Some(SpellProfit {
+        spell: spell.name.to_string(),
+        level: spell.level,
+        name: recipe.name.clone(),
+        id: recipe.id,
+        icon: recipe.icon.clone(),
+        members: spell.members || recipe.members,
+        inputs,
+        staff,
+        outputs: recipe.outputs,
+        price,
+        input_cost,
+        rune_cost,
+        coin_cost,
+        tax: item_tax,
+        profit,
+        profit_margin: profit as f64 * 100_f64 / (price as f64 * recipe.outputs as f64),
+        skill_xp,
+        total_xp,
+        casts_per_hour,
+        gp_per_hour: (profit as f64 * casts_per_hour).round() as i64,
+        casts_per_limit,
+        limit_profit: casts_per_limit.map(|l| l * profit),
+        volume: volume.get(&recipe.id).map(|v| v.total()),
+        last_trade,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(high: &[(i64, i64)]) -> HashMap<i64, GePrice> {
+        high.iter()
+            .map(|(id, p)| {
+                (
+                    *id,
+                    GePrice {
+                        high: Some(*p),
+                        low: Some(p - 1),
+                        ..GePrice::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn cost(setup: RuneSetup, runes: &[(i64, i64)], ge: &HashMap<i64, GePrice>) -> i64 {
+        setup.cast_cost(runes, ge, &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn fire_runes_come_from_staff_or_tome() {
+        let ge = prices(&[(NATURE_RUNE, 200), (FIRE_RUNE, 5)]);
+
+        let fire = RuneSetup::default();
+        assert_eq!(cost(fire, &HIGH_ALCH_RUNES, &ge), 200);
+
+        let none = RuneSetup {
+            staff: Staff::None,
+            ..RuneSetup::default()
+        };
+        assert_eq!(cost(none, &HIGH_ALCH_RUNES, &ge), 225);
+        assert_eq!(cost(none, &LOW_ALCH_RUNES, &ge), 215);
+
+        let tome = RuneSetup {
+            tome_of_fire: true,
+            ..none
+        };
+        assert_eq!(cost(tome, &HIGH_ALCH_RUNES, &ge), 200);
+    }
+
+    #[test]
+    fn bryophyta_saves_one_nature_rune_in_fifteen() {
+        let ge = prices(&[(NATURE_RUNE, 150), (FIRE_RUNE, 5)]);
+
+        let staff = RuneSetup {
+            staff: Staff::Bryophyta,
+            ..RuneSetup::default()
+        };
+        assert_eq!(cost(staff, &HIGH_ALCH_RUNES, &ge), 140 + 25);
+
+        let tome = RuneSetup {
+            tome_of_fire: true,
+            ..staff
+        };
+        assert_eq!(cost(tome, &HIGH_ALCH_RUNES, &ge), 140);
+
+        // 200 * 14 / 15 = 186.67
+        let ge = prices(&[(NATURE_RUNE, 200), (FIRE_RUNE, 5)]);
+        assert_eq!(cost(tome, &HIGH_ALCH_RUNES, &ge), 187);
+    }
+
+    #[test]
+    fn spells_use_their_own_elemental_staff() {
+        let ge = prices(&[
+            (ASTRAL_RUNE, 150),
+            (NATURE_RUNE, 200),
+            (EARTH_RUNE, 4),
+            (WATER_RUNE, 5),
+        ]);
+        let spell = |name: &str| SPELLS.iter().find(|d| d.name == name).unwrap();
+
+        let plank = spell("Plank Make");
+        let setup = RuneSetup::default().for_spell(plank);
+        assert_eq!(setup.staff, Staff::Earth);
+        assert_eq!(cost(setup, plank.runes, &ge), 500);
+
+        let string = spell("String Jewellery");
+        let setup = RuneSetup::default().for_spell(string);
+        assert_eq!(setup.staff, Staff::Mud);
+        assert_eq!(cost(setup, string.runes, &ge), 300);
+
+        // Any other staff is kept as chosen
+        let none = RuneSetup {
+            staff: Staff::None,
+            ..RuneSetup::default()
+        };
+        assert_eq!(none.for_spell(plank), none);
+        assert_eq!(cost(none.for_spell(plank), plank.runes, &ge), 560);
+    }
+
+    #[test]
+    fn free_runes_cost_nothing() {
+        let setup = RuneSetup {
+            staff: Staff::None,
+            free_runes: true,
+            ..RuneSetup::default()
+        };
+
+        assert_eq!(cost(setup, &HIGH_ALCH_RUNES, &HashMap::new()), 0);
+    }
+
+    #[test]
+    fn runes_use_the_chosen_basis() {
+        let ge = prices(&[(NATURE_RUNE, 200), (FIRE_RUNE, 5)]);
+        let setup = RuneSetup {
+            runes: PriceBasis::Low,
+            ..RuneSetup::default()
+        };
+
+        assert_eq!(cost(setup, &HIGH_ALCH_RUNES, &ge), 199);
+    }
+
+    #[test]
+    fn missing_rune_price_is_an_error() {
+        let ge = prices(&[(FIRE_RUNE, 5)]);
+
+        let res = RuneSetup::default().cast_cost(&HIGH_ALCH_RUNES, &ge, &HashMap::new());
+        assert!(matches!(
+            res,
+            Err(OsrsError::MissingReferencePrice(NATURE_RUNE))
+        ));
+
+        // Fire runes from the staff don't need a price
+        let ge = prices(&[(NATURE_RUNE, 200)]);
+        assert_eq!(cost(RuneSetup::default(), &HIGH_ALCH_RUNES, &ge), 200);
+
+        let none = RuneSetup {
+            staff: Staff::None,
+            ..RuneSetup::default()
+        };
+        let res = none.cast_cost(&HIGH_ALCH_RUNES, &ge, &HashMap::new());
+        assert!(matches!(
+            res,
+            Err(OsrsError::MissingReferencePrice(FIRE_RUNE))
+        ));
+    }
+}
@@ -0,0 +1,805 @@
+use crate::repo::data::chain::ChainProfit;
+use crate::repo::data::osrs::{CraftingItemProfit, FlipProfit, HighAlchProfit, LowAlchProfit};
+use crate::repo::data::pricing::{PriceBasis, Pricing, PRICE_BASES};
+use crate::repo::data::spell::{RuneSetup, SpellProfit, Staff, STAVES};
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use reqwest::Url;
+use serde::{Deserialize, Deserializer, Serialize};
+use validator::{Validate, ValidationError};
+
+const DEFAULT_PER_PAGE: usize = 50;
+
+/// Common view over every profit row so the tables can share one query implementation.
+/// Profit and margin are net of the GE tax where the row sells back to the exchange.
+pub trait ProfitRow {
+    /// Sort keys the table offers. Only rows with an action time or XP offer the rate sorts.
+    const SORT_KEYS: &'static [SortKey] = &SortKey::BASE;
+
+    fn name(&self) -> &str;
+    fn members(&self) -> bool;
+    fn ge_price(&self) -> i64;
+    fn net_profit(&self) -> i64;
+    fn net_margin(&self) -> f64;
+    /// Net profit over one GE buy limit window, if the buy limit is known.
+    fn limit_profit(&self) -> Option<i64>;
+    /// Items traded over the last hour, if the wiki reported any.
+    fn volume(&self) -> Option<i64>;
+    /// Unix time of the oldest trade the row's prices are based on.
+    fn last_trade(&self) -> Option<i64>;
+    /// Skills the row trains, for the skill filter.
+    fn skills(&self) -> Vec<&str> {
+        Vec::new()
+    }
+    /// Net profit over an hour of repeating the action, if its duration is known.
+    fn gp_per_hour(&self) -> Option<i64> {
+        None
+    }
+    /// XP per hour in `skill`, or in every skill the row trains when None.
+    fn xp_per_hour(&self, _skill: Option<&str>) -> Option<f64> {
+        None
+    }
+    /// Net profit per XP in `skill`, or in every skill when None. Negative is a cost.
+    fn gp_per_xp(&self, _skill: Option<&str>) -> Option<f64> {
+        None
+    }
+}
+
+impl ProfitRow for HighAlchProfit {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn members(&self) -> bool {
+        self.members
+    }
+    fn ge_price(&self) -> i64 {
+        self.ge_val
+    }
+    fn net_profit(&self) -> i64 {
+        self.profit_per_use
+    }
+    fn net_margin(&self) -> f64 {
+        self.profit_percent as f64
+    }
+    fn limit_profit(&self) -> Option<i64> {
+        self.limit_profit
+    }
+    fn volume(&self) -> Option<i64> {
+        self.volume
+    }
+    fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+}
+
+impl ProfitRow for LowAlchProfit {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn members(&self) -> bool {
+        self.members
+    }
+    fn ge_price(&self) -> i64 {
+        self.ge_val
+    }
+    fn net_profit(&self) -> i64 {
+        self.profit_per_use
+    }
+    fn net_margin(&self) -> f64 {
+        self.profit_percent as f64
+    }
+    fn limit_profit(&self) -> Option<i64> {
+        self.limit_profit
+    }
+    fn volume(&self) -> Option<i64> {
+        self.volume
+    }
+    fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+}
+
+impl ProfitRow for FlipProfit {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn members(&self) -> bool {
+        self.members
+    }
+    fn ge_price(&self) -> i64 {
+        self.low
+    }
+    fn net_profit(&self) -> i64 {
+        self.margin
+    }
+    fn net_margin(&self) -> f64 {
+        self.roi
+    }
+    fn limit_profit(&self) -> Option<i64> {
+        self.limit_profit
+    }
+    fn volume(&self) -> Option<i64> {
+        self.volume
+    }
+    fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+}
+
+impl ProfitRow for CraftingItemProfit {
+    const SORT_KEYS: &'static [SortKey] = &SortKey::ALL;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn members(&self) -> bool {
+        self.members == "Yes"
+    }
+    fn ge_price(&self) -> i64 {
+        self.price
+    }
+    fn net_profit(&self) -> i64 {
+        self.profit_after_tax
+    }
+    fn net_margin(&self) -> f64 {
+        self.profit_margin_after_tax as f64
+    }
+    fn limit_profit(&self) -> Option<i64> {
+        self.limit_profit
+    }
+    fn volume(&self) -> Option<i64> {
+        self.volume
+    }
+    fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+    fn skills(&self) -> Vec<&str> {
+        self.skill_names()
+    }
+    fn gp_per_hour(&self) -> Option<i64> {
+        self.gp_per_hour
+    }
+    fn xp_per_hour(&self, skill: Option<&str>) -> Option<f64> {
+        CraftingItemProfit::xp_per_hour(self, skill)
+    }
+    fn gp_per_xp(&self, skill: Option<&str>) -> Option<f64> {
+        CraftingItemProfit::gp_per_xp(self, skill)
+    }
+}
+
+impl ProfitRow for ChainProfit {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn members(&self) -> bool {
+        self.members
+    }
+    fn ge_price(&self) -> i64 {
+        self.price
+    }
+    fn net_profit(&self) -> i64 {
+        self.profit_after_tax
+    }
+    fn net_margin(&self) -> f64 {
+        self.profit_margin_after_tax
+    }
+    fn limit_profit(&self) -> Option<i64> {
+        self.limit_profit
+    }
+    fn volume(&self) -> Option<i64> {
+        self.volume
+    }
+    fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+    fn skills(&self) -> Vec<&str> {
+        self.xp.iter().map(|d| d.skill.as_str()).collect()
+    }
+}
+
+impl ProfitRow for SpellProfit {
+    const SORT_KEYS: &'static [SortKey] = &SortKey::ALL;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn members(&self) -> bool {
+        self.members
+    }
+    fn ge_price(&self) -> i64 {
+        self.price
+    }
+    fn net_profit(&self) -> i64 {
+        self.profit
+    }
+    fn net_margin(&self) -> f64 {
+        self.profit_margin
+    }
+    fn limit_profit(&self) -> Option<i64> {
+        self.limit_profit
+    }
+    fn volume(&self) -> Option<i64> {
+        self.volume
+    }
+    fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+    fn skills(&self) -> Vec<&str> {
+        self.skill_names()
+    }
+    fn gp_per_hour(&self) -> Option<i64> {
+        Some(self.gp_per_hour)
+    }
+    fn xp_per_hour(&self, skill: Option<&str>) -> Option<f64> {
+        SpellProfit::xp_per_hour(self, skill)
+    }
+    fn gp_per_xp(&self, skill: Option<&str>) -> Option<f64> {
+        SpellProfit::gp_per_xp(self, skill)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Profit,
+    Margin,
+    GePrice,
+    Name,
+    LimitProfit,
+    Volume,
+    LastTrade,
+    GpPerHour,
+    XpPerHour,
+    GpPerXp,
+}
+
+impl SortKey {
+    const BASE: [SortKey; 7] = [
+        SortKey::Profit,
+        SortKey::Margin,
+        SortKey::GePrice,
+        SortKey::Name,
+        SortKey::LimitProfit,
+        SortKey::Volume,
+        SortKey::LastTrade,
+    ];
+
+    const ALL: [SortKey; 10] = [
+        SortKey::Profit,
+        SortKey::Margin,
+        SortKey::GePrice,
+        SortKey::Name,
+        SortKey::LimitProfit,
+        SortKey::Volume,
+        SortKey::LastTrade,
+        SortKey::GpPerHour,
+        SortKey::XpPerHour,
+        SortKey::GpPerXp,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Profit => "profit",
+            SortKey::Margin => "margin",
+            SortKey::GePrice => "ge_price",
+            SortKey::Name => "name",
+            SortKey::LimitProfit => "limit_profit",
+            SortKey::Volume => "volume",
+            SortKey::LastTrade => "last_trade",
+            SortKey::GpPerHour => "gp_per_hour",
+            SortKey::XpPerHour => "xp_per_hour",
+            SortKey::GpPerXp => "gp_per_xp",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Profit => "Profit",
+            SortKey::Margin => "Profit margin",
+            SortKey::GePrice => "GE Price",
+            SortKey::Name => "Name",
+            SortKey::LimitProfit => "Profit per buy limit",
+            SortKey::Volume => "Volume (1h)",
+            SortKey::LastTrade => "Last trade",
+            SortKey::GpPerHour => "GP/hour",
+            SortKey::XpPerHour => "XP/hour",
+            SortKey::GpPerXp => "GP per XP",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Desc,
+    Asc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Desc => "desc",
+            SortOrder::Asc => "asc",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MembersFilter {
+    #[default]
+    All,
+    Members,
+    F2p,
+}
+
+impl MembersFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MembersFilter::All => "all",
+            MembersFilter::Members => "members",
+            MembersFilter::F2p => "f2p",
+        }
+    }
+}
+
+/// Query parameters accepted by every profit table, both HTML and JSON.
+#[derive(Deserialize, Validate, Clone, Debug, Default)]
+#[validate(schema(function = "validate_price_range"))]
+pub struct TableQuery {
+    #[serde(default)]
+    pub sort: SortKey,
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
+    pub members: MembersFilter,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(range(min = 0))]
+    pub min_price: Option<i64>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(range(min = 0))]
+    pub max_price: Option<i64>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub min_profit: Option<i64>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(range(min = 0))]
+    pub min_volume: Option<i64>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(length(max = 64))]
+    pub name: Option<String>,
+    /// Only rows that train this skill; also picks the skill the XP sorts use.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(length(max = 32))]
+    pub skill: Option<String>,
+    /// Price inputs are bought at, on the tables that buy something.
+    #[serde(default)]
+    pub buy: PriceBasis,
+    /// Price outputs are valued at, on the tables that sell something back to the GE.
+    #[serde(default)]
+    pub sell: PriceBasis,
+    /// Alching setup, see `RuneSetup`.
+    #[serde(default)]
+    pub staff: Staff,
+    #[serde(default)]
+    pub tome_of_fire: bool,
+    #[serde(default)]
+    pub runes: PriceBasis,
+    #[serde(default)]
+    pub free_runes: bool,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(range(min = 1))]
+    pub page: Option<usize>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[validate(range(min = 1, max = 500))]
+    pub per_page: Option<usize>,
+}
+
+fn validate_price_range(query: &TableQuery) -> Result<(), ValidationError> {
+    match (query.min_price, query.max_price) {
+        (Some(min), Some(max)) if min > max => {
+            Err(ValidationError::new("min_price_above_max_price"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// HTML forms send empty inputs as `key=`, which should mean "not set" rather than a parse error.
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let raw: Option<String> = Option::deserialize(deserializer)?;
+    match raw {
+        Some(e) if !e.trim().is_empty() => match e.trim().parse() {
+            Ok(v) => Ok(Some(v)),
+            Err(err) => Err(serde::de::Error::custom(err)),
+        },
+        _ => Ok(None),
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+    pub pages: usize,
+}
+
+impl<T: ProfitRow> Page<T> {
+    /// `(value, label, selected)` for the sort dropdown, limited to what the rows can sort by.
+    pub fn sort_options(&self, sort: &SortKey) -> Vec<(&'static str, &'static str, bool)> {
+        T::SORT_KEYS
+            .iter()
+            .map(|e| (e.as_str(), e.label(), e == sort))
+            .collect()
+    }
+}
+
+impl TableQuery {
+    /// Rejects a sort the table's rows have no value for, like GP/hour on the alch tables.
+    pub fn validate_sort<T: ProfitRow>(&self) -> Result<(), String> {
+        match T::SORT_KEYS.contains(&self.sort) {
+            true => Ok(()),
+            false => Err(format!(
+                "This table can't be sorted by {}",
+                self.sort.label()
+            )),
+        }
+    }
+
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1)
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE)
+    }
+
+    pub fn pricing(&self) -> Pricing {
+        Pricing {
+            buy: self.buy,
+            sell: self.sell,
+        }
+    }
+
+    pub fn rune_setup(&self) -> RuneSetup {
+        RuneSetup {
+            staff: self.staff,
+            tome_of_fire: self.tome_of_fire,
+            runes: self.runes,
+            free_runes: self.free_runes,
+        }
+    }
+
+    /// Filters, sorts and paginates rows according to the query.
+    pub fn apply<T: ProfitRow>(&self, rows: Vec<T>) -> Page<T> {
+        let name = self.name.as_ref().map(|e| e.to_lowercase());
+
+        let mut rows: Vec<T> = rows
+            .into_iter()
+            .filter(|r| match self.members {
+                MembersFilter::All => true,
+                MembersFilter::Members => r.members(),
+                MembersFilter::F2p => !r.members(),
+            })
+            .filter(|r| self.min_price.is_none_or(|e| r.ge_price() >= e))
+            .filter(|r| self.max_price.is_none_or(|e| r.ge_price() <= e))
+            .filter(|r| self.min_profit.is_none_or(|e| r.net_profit() >= e))
+            .filter(|r| self.min_volume.is_none_or(|e| r.volume().unwrap_or(0) >= e))
+            .filter(|r| match &name {
+                Some(e) => r.name().to_lowercase().contains(e),
+                None => true,
+            })
+            .filter(|r| match &self.skill {
+                Some(e) => r.skills().iter().any(|s| s.eq_ignore_ascii_case(e)),
+                None => true,
+            })
+            .collect();
+
+        let skill = self.skill.as_deref();
+
+        rows.sort_by(|a, b| {
+            let ord = match self.sort {
+                SortKey::Profit => a.net_profit().cmp(&b.net_profit()),
+                SortKey::Margin => a
+                    .net_margin()
+                    .partial_cmp(&b.net_margin())
+                    .unwrap_or(Ordering::Equal),
+                SortKey::GePrice => a.ge_price().cmp(&b.ge_price()),
+                SortKey::Name => a.name().cmp(b.name()),
+                SortKey::LimitProfit => match (a.limit_profit(), b.limit_profit()) {
+                    (Some(x), Some(y)) => x.cmp(&y),
+                    // Rows without a known buy limit always sort last
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                SortKey::GpPerHour => match (a.gp_per_hour(), b.gp_per_hour()) {
+                    (Some(x), Some(y)) => x.cmp(&y),
+                    // Rows without a known action time always sort last
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                SortKey::XpPerHour => match (a.xp_per_hour(skill), b.xp_per_hour(skill)) {
+                    (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                SortKey::GpPerXp => match (a.gp_per_xp(skill), b.gp_per_xp(skill)) {
+                    (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+                    // Rows that give no XP always sort last
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                SortKey::Volume => a.volume().unwrap_or(0).cmp(&b.volume().unwrap_or(0)),
+                SortKey::LastTrade => a
+                    .last_trade()
+                    .unwrap_or(0)
+                    .cmp(&b.last_trade().unwrap_or(0)),
+            };
+            match self.order {
+                SortOrder::Asc => ord,
+                SortOrder::Desc => ord.reverse(),
+            }
+        });
+
+        let total = rows.len();
+        let per_page = self.per_page();
+        let pages = total.div_ceil(per_page).max(1);
+        let page = self.page().min(pages);
+
+        let items = rows
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+
+        Page {
+            items,
+            page,
+            per_page,
+            total,
+            pages,
+        }
+    }
+
+    /// Query string for the same table on another page, used by the pagination links.
+    pub fn link(&self, page: &usize) -> String {
+        let mut url = Url::parse("http://localhost/").unwrap();
+        {
+            let mut q = url.query_pairs_mut();
+            q.append_pair("sort", self.sort.as_str());
+            q.append_pair("order", self.order.as_str());
+            q.append_pair("members", self.members.as_str());
+            if let Some(e) = self.min_price {
+                q.append_pair("min_price", &e.to_string());
+            }
+            if let Some(e) = self.max_price {
+                q.append_pair("max_price", &e.to_string());
+            }
+            if let Some(e) = self.min_profit {
+                q.append_pair("min_profit", &e.to_string());
+            }
+            if let Some(e) = self.min_volume {
+                q.append_pair("min_volume", &e.to_string());
+            }
+            if let Some(e) = &self.name {
+                q.append_pair("name", e);
+            }
+            if let Some(e) = &self.skill {
+                q.append_pair("skill", e);
+            }
+            if !self.pricing().is_default() {
+                q.append_pair("buy", self.buy.as_str());
+                q.append_pair("sell", self.sell.as_str());
+            }
+            if !self.rune_setup().is_default() {
+                q.append_pair("staff", self.staff.as_str());
+                q.append_pair("tome_of_fire", &self.tome_of_fire.to_string());
+                q.append_pair("runes", self.runes.as_str());
+                q.append_pair("free_runes", &self.free_runes.to_string());
+            }
+            q.append_pair("page", &page.to_string());
+            q.append_pair("per_page", &self.per_page().to_string());
+        }
+        format!("?{}", url.query().unwrap_or_default())
+    }
+
+    /// `(value, label, selected)` for the price basis dropdowns.
+    pub fn basis_options(&self, selected: &PriceBasis) -> Vec<(&'static str, &'static str, bool)> {
+        PRICE_BASES
+            .iter()
+            .map(|e| (e.as_str(), e.label(), e == selected))
+            .collect()
+    }
+
+    /// `(value, label, selected)` for the staff dropdown. With `per_spell` the fire staff
+    /// stands for the elemental staff that suits each spell, see `RuneSetup::for_spell`.
+    pub fn staff_options(&self, per_spell: &bool) -> Vec<(&'static str, &'static str, bool)> {
+        STAVES
+            .iter()
+            .map(|e| {
+                let label = match (per_spell, e) {
+                    (true, Staff::Fire) => "Elemental staff for the spell",
+                    _ => e.label(),
+                };
+                (e.as_str(), label, *e == self.staff)
+            })
+            .collect()
+    }
+
+    pub fn opt_value(&self, v: &Option<i64>) -> String {
+        match v {
+            Some(e) => e.to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, members: bool, ge_val: i64, profit: i64) -> HighAlchProfit {
+        HighAlchProfit {
+            name: name.to_string(),
+            members,
+            ge_val,
+            profit_per_use: profit,
+            profit_percent: profit * 100 / ge_val,
+            ..HighAlchProfit::default()
+        }
+    }
+
+    fn rows() -> Vec<HighAlchProfit> {
+        vec![
+            row("Rune platebody", true, 38_000, 800),
+            row("Rune scimitar", false, 15_000, 300),
+            row("Adamant platebody", false, 9_500, -100),
+            row("Dragon dagger", true, 17_000, 1_200),
+            row("Mithril bar", false, 1_000, 50),
+        ]
+    }
+
+    fn names(page: &Page<HighAlchProfit>) -> Vec<&str> {
+        page.items.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_by_profit_descending_by_default() {
+        let page = TableQuery::default().apply(rows());
+
+        assert_eq!(
+            names(&page),
+            [
+                "Dragon dagger",
+                "Rune platebody",
+                "Rune scimitar",
+                "Mithril bar",
+                "Adamant platebody"
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_by_any_key_in_either_order() {
+        let query = TableQuery {
+            sort: SortKey::Name,
+            order: SortOrder::Asc,
+            ..TableQuery::default()
+        };
+        assert_eq!(names(&query.apply(rows()))[0], "Adamant platebody");
+
+        let query = TableQuery {
+            sort: SortKey::GePrice,
+            order: SortOrder::Asc,
+            ..TableQuery::default()
+        };
+        assert_eq!(names(&query.apply(rows()))[0], "Mithril bar");
+    }
+
+    #[test]
+    fn filters_members_price_profit_and_name() {
+        let query = TableQuery {
+            members: MembersFilter::F2p,
+            min_price: Some(5_000),
+            ..TableQuery::default()
+        };
+        assert_eq!(
+            names(&query.apply(rows())),
+            ["Rune scimitar", "Adamant platebody"]
+        );
+
+        let query = TableQuery {
+            members: MembersFilter::Members,
+            max_price: Some(20_000),
+            ..TableQuery::default()
+        };
+        assert_eq!(names(&query.apply(rows())), ["Dragon dagger"]);
+
+        let query = TableQuery {
+            min_profit: Some(0),
+            name: Some("RUNE".to_string()),
+            ..TableQuery::default()
+        };
+        assert_eq!(
+            names(&query.apply(rows())),
+            ["Rune platebody", "Rune scimitar"]
+        );
+    }
+
+    #[test]
+    fn paginates_and_clamps_to_last_page() {
+        let query = TableQuery {
+            per_page: Some(2),
+            page: Some(3),
+            ..TableQuery::default()
+        };
+        let page = query.apply(rows());
+        assert_eq!((page.page, page.pages, page.total), (3, 3, 5));
+        assert_eq!(names(&page), ["Adamant platebody"]);
+
+        let query = TableQuery {
+            page: Some(40),
+            ..query
+        };
+        assert_eq!(query.apply(rows()).page, 3);
+
+        let page = TableQuery::default().apply(Vec::<HighAlchProfit>::new());
+        assert_eq!((page.page, page.pages, page.total), (1, 1, 0));
+    }
+
+    #[test]
+    fn link_keeps_the_query_on_another_page() {
+        let query = TableQuery {
+            sort: SortKey::Margin,
+            order: SortOrder::Asc,
+            members: MembersFilter::F2p,
+            min_price: Some(100),
+            name: Some("rune & dragon".to_string()),
+            per_page: Some(20),
+            ..TableQuery::default()
+        };
+
+        assert_eq!(
+            query.link(&2),
+            "?sort=margin&order=asc&members=f2p&min_price=100&name=rune+%26+dragon&page=2&per_page=20"
+        );
+        assert_eq!(
+            TableQuery::default().link(&1),
+            "?sort=profit&order=desc&members=all&page=1&per_page=50"
+        );
+    }
+
+    #[test]
+    fn validate_sort_only_accepts_keys_the_rows_have() {
+        let query = TableQuery {
+            sort: SortKey::GpPerHour,
+            ..TableQuery::default()
+        };
+        assert_eq!(
+            query.validate_sort::<HighAlchProfit>(),
+            Err("This table can't be sorted by GP/hour".to_string())
+        );
+        assert_eq!(query.validate_sort::<CraftingItemProfit>(), Ok(()));
+
+        let query = TableQuery {
+            sort: SortKey::LastTrade,
+            ..TableQuery::default()
+        };
+        assert_eq!(query.validate_sort::<HighAlchProfit>(), Ok(()));
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// Items the Grand Exchange never taxes: old school bonds and the cheap skilling tools.
+const EXEMPT_ITEMS: [i64; 14] = [
+    13190, // Old school bond
+    1755,  // Chisel
+    5325,  // Gardening trowel
+    1785,  // Glassblowing pipe
+    2347,  // Hammer
+    1733,  // Needle
+    233,   // Pestle and mortar
+    5341,  // Rake
+    8794,  // Saw
+    5329,  // Secateurs
+    5343,  // Seed dibber
+    1735,  // Shears
+    952,   // Spade
+    5331,  // Watering can(0)
+];
+
+/// Grand Exchange sell tax. A percentage of each sold item is taken, rounded down and capped
+/// per item. Items sold below `min_price` and items in `exempt` are not taxed.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeTax {
+    pub percent: f64,
+    pub cap: i64,
+    pub min_price: i64,
+    pub exempt: HashSet<i64>,
+}
+
+impl Default for GeTax {
+    fn default() -> Self {
+        GeTax {
+            percent: 2.0,
+            cap: 5_000_000,
+            min_price: 50,
+            exempt: HashSet::from(EXEMPT_ITEMS),
+        }
+    }
+}
+
+impl GeTax {
+    /// Builds the tax from `GE_TAX_PERCENT`, `GE_TAX_CAP`, `GE_TAX_MIN_PRICE` and
+    /// `GE_TAX_EXEMPT` (comma separated item ids), falling back to the current Jagex values.
+    pub fn from_env() -> Self {
+        let mut tax = GeTax::default();
+
+        tax.percent = env_or("GE_TAX_PERCENT", tax.percent);
+        tax.cap = env_or("GE_TAX_CAP", tax.cap);
+        tax.min_price = env_or("GE_TAX_MIN_PRICE", tax.min_price);
+
+        if let Ok(e) = env::var("GE_TAX_EXEMPT") {
+            let mut exempt = HashSet::new();
+            for id in e.split(',').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+                match id.parse() {
+                    Ok(i) => {
+                        exempt.insert(i);
+                    }
+                    Err(_) => println!("Ignoring invalid item id \"{}\" in GE_TAX_EXEMPT", id),
+                }
+            }
+            tax.exempt = exempt;
+        }
+
+        tax
+    }
+
+    /// Tax taken when selling one `item` at `price`.
+    pub fn tax(&self, item: &i64, price: i64) -> i64 {
+        if price < self.min_price || self.exempt.contains(item) {
+            return 0;
+        }
+
+        let tax = (price as f64 * self.percent / 100_f64).floor() as i64;
+
+        tax.min(self.cap)
+    }
+
+    /// What the seller actually receives for one `item` sold at `price`.
+    pub fn after_tax(&self, item: &i64, price: i64) -> i64 {
+        price - self.tax(item, price)
+    }
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match env::var(key) {
+        Ok(e) => match e.parse() {
+            Ok(v) => v,
+            Err(_) => {
+                println!("Ignoring invalid value \"{}\" for {}", e, key);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITEM: i64 = 4151; // Abyssal whip, not exempt
+
+    #[test]
+    fn takes_percentage_rounded_down() {
+        let tax = GeTax::default();
+
+        assert_eq!(tax.tax(&ITEM, 100), 2);
+        assert_eq!(tax.tax(&ITEM, 149), 2);
+        assert_eq!(tax.tax(&ITEM, 150), 3);
+        assert_eq!(tax.after_tax(&ITEM, 1_000_000), 980_000);
+    }
+
+    #[test]
+    fn caps_tax_per_item() {
+        let tax = GeTax::default();
+
+        assert_eq!(tax.tax(&ITEM, 250_000_000), 5_000_000);
+        assert_eq!(tax.tax(&ITEM, 2_147_483_647), 5_000_000);
+        assert_eq!(tax.after_tax(&ITEM, 500_000_000), 495_000_000);
+    }
+
+    #[test]
+    fn skips_items_below_min_price() {
+        let tax = GeTax::default();
+
+        assert_eq!(tax.tax(&ITEM, 49), 0);
+        assert_eq!(tax.tax(&ITEM, 50), 1);
+        assert_eq!(tax.after_tax(&ITEM, 10), 10);
+    }
+
+    #[test]
+    fn skips_exempt_items() {
+        let tax = GeTax::default();
+
+        assert_eq!(tax.tax(&13190, 10_000_000), 0);
+        assert_eq!(tax.tax(&2347, 1_000), 0);
+
+        let custom = GeTax {
+            exempt: HashSet::from([ITEM]),
+            ..GeTax::default()
+        };
+        assert_eq!(custom.tax(&ITEM, 1_000_000), 0);
+        assert_eq!(custom.tax(&13190, 1_000_000), 20_000);
+    }
+}
@@ -0,0 +1,82 @@
+use std::env;
+
+const DEFAULT_PRICES_URL: &str = "https://prices.runescape.wiki/api/v1/osrs";
+const DEFAULT_WIKI_URL: &str = "https://oldschool.runescape.wiki";
+const DEFAULT_USER_AGENT: &str = "gecalculator - ellabella on discord";
+const DEFAULT_RECIPE_REFRESH_HOURS: u64 = 24;
+
+/// Where price, mapping and production data is fetched from. Defaults to the real wiki;
+/// point the urls at the `wiki_fixtures` binary to run without network access.
+#[derive(Clone, Debug)]
+pub struct Upstream {
+    pub prices_url: String,
+    pub wiki_url: String,
+    pub user_agent: String,
+    /// How long stored production recipes are used before they are fetched again.
+    pub recipe_refresh_hours: u64,
+}
+
+impl Default for Upstream {
+    fn default() -> Self {
+        Upstream {
+            prices_url: DEFAULT_PRICES_URL.to_string(),
+            wiki_url: DEFAULT_WIKI_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            recipe_refresh_hours: DEFAULT_RECIPE_REFRESH_HOURS,
+        }
+    }
+}
+
+impl Upstream {
+    /// Reads `OSRS_PRICES_URL`, `OSRS_WIKI_URL`, `OSRS_USER_AGENT` and
+    /// `RECIPE_REFRESH_HOURS`, keeping the defaults for anything unset.
+    pub fn from_env() -> Self {
+        let default = Upstream::default();
+
+        Upstream {
+            prices_url: env_url("OSRS_PRICES_URL", default.prices_url),
+            wiki_url: env_url("OSRS_WIKI_URL", default.wiki_url),
+            user_agent: env::var("OSRS_USER_AGENT").unwrap_or(default.user_agent),
+            recipe_refresh_hours: match env::var("RECIPE_REFRESH_HOURS") {
+                Ok(e) => match e.parse() {
+                    Ok(v) if v > 0 => v,
+                    _ => {
+                        println!("Ignoring invalid value \"{}\" for RECIPE_REFRESH_HOURS", e);
+                        default.recipe_refresh_hours
+                    }
+                },
+                Err(_) => default.recipe_refresh_hours,
+            },
+        }
+    }
+
+    pub fn mapping_url(&self) -> String {
+        format!("{}/mapping", self.prices_url)
+    }
+
+    pub fn latest_url(&self) -> String {
+        format!("{}/latest", self.prices_url)
+    }
+
+    pub fn volume_url(&self, timestep: &str) -> String {
+        format!("{}/{}", self.prices_url, timestep)
+    }
+
+    pub fn timeseries_url(&self, id: &i64, timestep: &str) -> String {
+        format!(
+            "{}/timeseries?id={}&timestep={}",
+            self.prices_url, id, timestep
+        )
+    }
+
+    pub fn production_url(&self, offset: usize) -> String {
+        format!("{}/w/Special:Ask/class%3Dsortable-20wikitable-20smwtable/format%3Djson/headers%3Dshow/link%3Dall/mainlabel%3D/searchlabel%3DJSON/sort%3D/order%3Dasc/offset%3D{}/limit%3D500/-5B-5BProduction-20JSON::%2B-5D-5D/-3FProduction-20JSON/prettyprint%3Dtrue/unescape%3Dtrue", self.wiki_url, offset)
+    }
+}
+
+fn env_url(key: &str, default: String) -> String {
+    match env::var(key) {
+        Ok(e) => e.trim_end_matches('/').to_string(),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,151 @@
+use crate::repo::sql::Database;
+
+use std::env;
+use std::time::Duration;
+
+use chrono::Utc;
+
+const DEFAULT_MAINTENANCE_HOURS: u64 = 1;
+
+/// A partman managed table, the column it is partitioned on and how many days of it to keep.
+#[derive(Clone, Debug)]
+pub struct RetentionPolicy {
+    pub table: &'static str,
+    pub control: &'static str,
+    pub env: &'static str,
+    pub days: Option<i64>,
+}
+
+/// How often partition maintenance runs and how long each table is kept. Raw 5 minute data
+/// expires, hourly data and the rollups are kept forever unless configured otherwise.
+#[derive(Clone, Debug)]
+pub struct Retention {
+    pub interval_hours: u64,
+    pub policies: Vec<RetentionPolicy>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            interval_hours: DEFAULT_MAINTENANCE_HOURS,
+            policies: vec![
+                RetentionPolicy {
+                    table: "ge.price",
+                    control: "created",
+                    env: "RETENTION_PRICE_DAYS",
+                    days: Some(90),
+                },
+                RetentionPolicy {
+                    table: "ge.volume_5m",
+                    control: "bucket",
+                    env: "RETENTION_VOLUME_5M_DAYS",
+                    days: Some(90),
+                },
+                RetentionPolicy {
+                    table: "ge.volume_1h",
+                    control: "bucket",
+                    env: "RETENTION_VOLUME_1H_DAYS",
+                    days: None,
+                },
+                RetentionPolicy {
+                    table: "ge.price_1h",
+                    control: "bucket",
+                    env: "RETENTION_PRICE_1H_DAYS",
+                    days: None,
+                },
+            ],
+        }
+    }
+}
+
+impl Retention {
+    /// Reads `MAINTENANCE_INTERVAL_HOURS` and each table's `RETENTION_*_DAYS`, where `0` or
+    /// `forever` keeps the table forever.
+    pub fn from_env() -> Self {
+        let mut retention = Retention::default();
+
+        if let Ok(e) = env::var("MAINTENANCE_INTERVAL_HOURS") {
+            match e.parse() {
+                Ok(v) if v > 0 => retention.interval_hours = v,
+                _ => println!(
+                    "Ignoring invalid value \"{}\" for MAINTENANCE_INTERVAL_HOURS",
+                    e
+                ),
+            }
+        }
+
+        for p in retention.policies.iter_mut() {
+            if let Ok(e) = env::var(p.env) {
+                match e.trim() {
+                    "0" | "forever" => p.days = None,
+                    d => match d.parse() {
+                        Ok(v) if v > 0 => p.days = Some(v),
+                        _ => println!("Ignoring invalid value \"{}\" for {}", e, p.env),
+                    },
+                }
+            }
+        }
+
+        retention
+    }
+
+    /// Applies the retention settings to partman, then runs maintenance straight away and
+    /// every `interval_hours` after that.
+    pub async fn schedule(self, database: Database) {
+        for p in &self.policies {
+            match database.set_partman_retention(p.table, p.days).await {
+                Ok(_) => match p.days {
+                    Some(d) => println!("keeping {} days of {}", d, p.table),
+                    None => println!("keeping all of {}", p.table),
+                },
+                Err(e) => println!("cannot set retention for {}: {}", p.table, e),
+            };
+        }
+
+        loop {
+            self.run(&database).await;
+            tokio::time::sleep(Duration::from_secs(self.interval_hours * 3600)).await;
+        }
+    }
+
+    async fn run(&self, database: &Database) {
+        println!("running partition maintenance");
+
+        let mut before: Vec<Vec<String>> = Vec::new();
+        for p in &self.policies {
+            before.push(database.get_partitions(p.table).await.unwrap_or_default());
+        }
+
+        match database.run_partman_maintenance().await {
+            Ok(_) => (),
+            Err(e) => println!("partition maintenance failed: {}", e),
+        };
+
+        for (p, before) in self.policies.iter().zip(before) {
+            let after = database.get_partitions(p.table).await.unwrap_or_default();
+
+            for d in before.iter().filter(|d| !after.contains(d)) {
+                println!("dropped expired partition {}", d);
+            }
+            for d in after.iter().filter(|d| !before.contains(d)) {
+                println!("created partition {}", d);
+            }
+
+            let days = match p.days {
+                Some(e) => e,
+                None => continue,
+            };
+
+            let cutoff = Utc::now().naive_utc() - chrono::Duration::days(days);
+
+            match database
+                .purge_default_partition(p.table, p.control, &cutoff)
+                .await
+            {
+                Ok(0) => (),
+                Ok(e) => println!("deleted {} expired rows from {}_default", e, p.table),
+                Err(e) => println!("cannot purge {}_default: {}", p.table, e),
+            };
+        }
+    }
+}
@@ -1,11 +1,17 @@
-use crate::repo::data::osrs::GePrice;
+use crate::repo::data::alert::{AlertKind, AlertRule, NewAlertRule};
+use crate::repo::data::osrs::{
+    CraftingItem, CraftingMaterial, CraftingSkill, GePrice, GePriceCandle, GePriceHistory,
+    GeVolume, ItemChange, OsrsMap, PriceHistory, RecipeChange, Resolution, TimeseriesPoint,
+    TimeseriesTimestep, VolumeTimestep,
+};
 
 use std::collections::HashMap;
+use std::fmt;
 use std::path::Path;
 
+use serde_json::Value;
 use sqlx::migrate::Migrator;
 use sqlx::types::chrono::{NaiveDateTime, Utc};
-use sqlx::types::BigDecimal;
 use sqlx::Postgres;
 use sqlx::{PgPool, Pool};
 
@@ -15,41 +21,1452 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn new(database_url: String) -> Result<Self, String> {
+    pub async fn new(database_url: String) -> Result<Self, DatabaseErrors> {
         let migration_path = Path::new("./migrations");
 
-        let sql_pool = PgPool::connect(&database_url).await.unwrap();
+        let sql_pool = match PgPool::connect(&database_url).await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error connecting to database: {:?}", e);
+                return Err(DatabaseErrors::CannotConnect);
+            }
+        };
+
+        let migrator = match Migrator::new(migration_path).await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error loading migrations: {:?}", e);
+                return Err(DatabaseErrors::CannotMigrate);
+            }
+        };
+
+        match migrator.run(&sql_pool).await {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error running migrations: {:?}", e);
+                return Err(DatabaseErrors::CannotMigrate);
+            }
+        };
 
-        Migrator::new(migration_path)
-            .await
-            .unwrap()
-            .run(&sql_pool)
-            .await
-            .unwrap();
         return Ok(Database { database: sql_pool });
     }
 
+    /// Stores prices as a single batched insert inside a transaction, so either every price
+    /// from the refresh is stored or none are. Returns the rows written.
     pub async fn insert_ge_price_bulk(
         &self,
         ge_price: &HashMap<i64, GePrice>,
-    ) -> Result<(), DatabaseErrors> {
+    ) -> Result<u64, DatabaseErrors> {
+        if ge_price.is_empty() {
+            return Ok(0);
+        }
+
         let now = Utc::now().naive_utc();
+
+        let mut items: Vec<i64> = Vec::with_capacity(ge_price.len());
+        let mut high: Vec<Option<i64>> = Vec::with_capacity(ge_price.len());
+        let mut high_time: Vec<Option<i64>> = Vec::with_capacity(ge_price.len());
+        let mut low: Vec<Option<i64>> = Vec::with_capacity(ge_price.len());
+        let mut low_time: Vec<Option<i64>> = Vec::with_capacity(ge_price.len());
+
         for (k, d) in ge_price.iter() {
-            let high: Option<BigDecimal> = match d.high {
-                Some(e) => Some(BigDecimal::from(e)),
-                None => None,
+            items.push(*k);
+            high.push(d.high);
+            high_time.push(d.high_time);
+            low.push(d.low);
+            low_time.push(d.low_time);
+        }
+
+        let expected = items.len();
+        let failed = DatabaseErrors::CannotInsert {
+            table: "ge.price",
+            expected,
+            inserted: 0,
+        };
+
+        let mut tx = match self.database.begin().await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error starting price transaction: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let res = sqlx::query!(
+            "insert into ge.price(created, item, high, high_time, low, low_time) select $1, * from unnest($2::bigint[], $3::bigint[], $4::bigint[], $5::bigint[], $6::bigint[])",
+            &now,
+            &items,
+            &high as &[Option<i64>],
+            &high_time as &[Option<i64>],
+            &low as &[Option<i64>],
+            &low_time as &[Option<i64>]
+        )
+        .execute(&mut *tx)
+        .await;
+
+        let inserted = match res {
+            Ok(e) => e.rows_affected(),
+            Err(e) => {
+                println!("Error inserting {} prices: {:?}", expected, e);
+                return Err(failed);
+            }
+        };
+
+        // Returning without committing drops the transaction, which rolls it back
+        if inserted != expected as u64 {
+            return Err(DatabaseErrors::CannotInsert {
+                table: "ge.price",
+                expected,
+                inserted,
+            });
+        }
+
+        // Fold the new rows into the rollups. `greatest`/`least` ignore nulls, and open keeps
+        // the first known price while close takes the latest one.
+        let res = sqlx::query!(
+            "insert into ge.price_1h(bucket, item, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples)
+            select date_trunc('hour', $1::timestamp), item, high, high, high, high, low, low, low, low, 1
+            from unnest($2::bigint[], $3::bigint[], $4::bigint[]) as n(item, high, low)
+            on conflict (item, bucket) do update set
+                high_open = coalesce(ge.price_1h.high_open, excluded.high_open),
+                high_high = greatest(ge.price_1h.high_high, excluded.high_high),
+                high_low = least(ge.price_1h.high_low, excluded.high_low),
+                high_close = coalesce(excluded.high_close, ge.price_1h.high_close),
+                low_open = coalesce(ge.price_1h.low_open, excluded.low_open),
+                low_high = greatest(ge.price_1h.low_high, excluded.low_high),
+                low_low = least(ge.price_1h.low_low, excluded.low_low),
+                low_close = coalesce(excluded.low_close, ge.price_1h.low_close),
+                samples = ge.price_1h.samples + 1",
+            &now,
+            &items,
+            &high as &[Option<i64>],
+            &low as &[Option<i64>]
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error updating hourly rollup: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let res = sqlx::query!(
+            "insert into ge.price_1d(bucket, item, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples)
+            select date_trunc('day', $1::timestamp), item, high, high, high, high, low, low, low, low, 1
+            from unnest($2::bigint[], $3::bigint[], $4::bigint[]) as n(item, high, low)
+            on conflict (item, bucket) do update set
+                high_open = coalesce(ge.price_1d.high_open, excluded.high_open),
+                high_high = greatest(ge.price_1d.high_high, excluded.high_high),
+                high_low = least(ge.price_1d.high_low, excluded.high_low),
+                high_close = coalesce(excluded.high_close, ge.price_1d.high_close),
+                low_open = coalesce(ge.price_1d.low_open, excluded.low_open),
+                low_high = greatest(ge.price_1d.low_high, excluded.low_high),
+                low_low = least(ge.price_1d.low_low, excluded.low_low),
+                low_close = coalesce(excluded.low_close, ge.price_1d.low_close),
+                samples = ge.price_1d.samples + 1",
+            &now,
+            &items,
+            &high as &[Option<i64>],
+            &low as &[Option<i64>]
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error updating daily rollup: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error committing {} prices: {:?}", expected, e);
+                return Err(failed);
+            }
+        };
+
+        return Ok(inserted);
+    }
+
+    /// Stores one averaged bucket from the wiki `/5m` or `/1h` endpoint. Buckets already
+    /// stored for an item are left untouched so refetching the same window is harmless.
+    pub async fn insert_ge_volume_bulk(
+        &self,
+        timestep: &VolumeTimestep,
+        bucket: &NaiveDateTime,
+        volume: &HashMap<i64, GeVolume>,
+    ) -> Result<u64, DatabaseErrors> {
+        let mut items: Vec<i64> = Vec::with_capacity(volume.len());
+        let mut avg_high: Vec<Option<i64>> = Vec::with_capacity(volume.len());
+        let mut high_volume: Vec<i64> = Vec::with_capacity(volume.len());
+        let mut avg_low: Vec<Option<i64>> = Vec::with_capacity(volume.len());
+        let mut low_volume: Vec<i64> = Vec::with_capacity(volume.len());
+
+        for (k, d) in volume.iter() {
+            items.push(*k);
+            avg_high.push(d.avg_high_price);
+            high_volume.push(d.high_price_volume);
+            avg_low.push(d.avg_low_price);
+            low_volume.push(d.low_price_volume);
+        }
+
+        let res = match timestep {
+            VolumeTimestep::FiveMinute => sqlx::query!(
+                "insert into ge.volume_5m(bucket, item, avg_high_price, high_price_volume, avg_low_price, low_price_volume) select $1, * from unnest($2::bigint[], $3::bigint[], $4::bigint[], $5::bigint[], $6::bigint[]) on conflict (item, bucket) do nothing",
+                bucket,
+                &items,
+                &avg_high as &[Option<i64>],
+                &high_volume,
+                &avg_low as &[Option<i64>],
+                &low_volume
+            )
+            .execute(&self.database)
+            .await,
+            VolumeTimestep::OneHour => sqlx::query!(
+                "insert into ge.volume_1h(bucket, item, avg_high_price, high_price_volume, avg_low_price, low_price_volume) select $1, * from unnest($2::bigint[], $3::bigint[], $4::bigint[], $5::bigint[], $6::bigint[]) on conflict (item, bucket) do nothing",
+                bucket,
+                &items,
+                &avg_high as &[Option<i64>],
+                &high_volume,
+                &avg_low as &[Option<i64>],
+                &low_volume
+            )
+            .execute(&self.database)
+            .await,
+        };
+
+        match res {
+            Ok(e) => Ok(e.rows_affected()),
+            Err(e) => {
+                println!("Error inserting {} volume: {:?}", timestep.as_str(), e);
+                Err(DatabaseErrors::CannotInsert {
+                    table: timestep.table(),
+                    expected: items.len(),
+                    inserted: 0,
+                })
+            }
+        }
+    }
+
+    /// Stores one item's history from the wiki `/timeseries` endpoint. 5m and 1h points go
+    /// into the volume tables, and 1h and 24h points also seed the price rollups with the
+    /// bucket average as open/high/low/close and zero samples. Buckets that already exist,
+    /// whether recorded live or by an earlier backfill, are left untouched. The item is marked
+    /// as backfilled in the same transaction, even when it has no history at all.
+    pub async fn insert_timeseries(
+        &self,
+        item: &i64,
+        timestep: &TimeseriesTimestep,
+        points: &[TimeseriesPoint],
+    ) -> Result<u64, DatabaseErrors> {
+        let mut timestamps: Vec<i64> = Vec::with_capacity(points.len());
+        let mut avg_high: Vec<Option<i64>> = Vec::with_capacity(points.len());
+        let mut high_volume: Vec<i64> = Vec::with_capacity(points.len());
+        let mut avg_low: Vec<Option<i64>> = Vec::with_capacity(points.len());
+        let mut low_volume: Vec<i64> = Vec::with_capacity(points.len());
+
+        for d in points.iter() {
+            timestamps.push(d.timestamp);
+            avg_high.push(d.avg_high_price);
+            high_volume.push(d.high_price_volume);
+            avg_low.push(d.avg_low_price);
+            low_volume.push(d.low_price_volume);
+        }
+
+        let failed = |table: &'static str| DatabaseErrors::CannotInsert {
+            table,
+            expected: points.len(),
+            inserted: 0,
+        };
+
+        let mut tx = match self.database.begin().await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error starting timeseries transaction: {:?}", e);
+                return Err(failed("ge.backfill"));
+            }
+        };
+
+        let mut inserted = 0;
+
+        let volume = match timestep {
+            TimeseriesTimestep::FiveMinute => Some((
+                "ge.volume_5m",
+                sqlx::query!(
+                    "insert into ge.volume_5m(bucket, item, avg_high_price, high_price_volume, avg_low_price, low_price_volume) select to_timestamp(ts) at time zone 'utc', $1, ah, hv, al, lv from unnest($2::bigint[], $3::bigint[], $4::bigint[], $5::bigint[], $6::bigint[]) as t(ts, ah, hv, al, lv) on conflict (item, bucket) do nothing",
+                    item,
+                    &timestamps,
+                    &avg_high as &[Option<i64>],
+                    &high_volume,
+                    &avg_low as &[Option<i64>],
+                    &low_volume
+                )
+                .execute(&mut *tx)
+                .await,
+            )),
+            TimeseriesTimestep::OneHour => Some((
+                "ge.volume_1h",
+                sqlx::query!(
+                    "insert into ge.volume_1h(bucket, item, avg_high_price, high_price_volume, avg_low_price, low_price_volume) select to_timestamp(ts) at time zone 'utc', $1, ah, hv, al, lv from unnest($2::bigint[], $3::bigint[], $4::bigint[], $5::bigint[], $6::bigint[]) as t(ts, ah, hv, al, lv) on conflict (item, bucket) do nothing",
+                    item,
+                    &timestamps,
+                    &avg_high as &[Option<i64>],
+                    &high_volume,
+                    &avg_low as &[Option<i64>],
+                    &low_volume
+                )
+                .execute(&mut *tx)
+                .await,
+            )),
+            TimeseriesTimestep::OneDay => None,
+        };
+
+        if let Some((table, res)) = volume {
+            match res {
+                Ok(e) => inserted += e.rows_affected(),
+                Err(e) => {
+                    println!("Error backfilling {} for {}: {:?}", table, item, e);
+                    return Err(failed(table));
+                }
+            };
+        }
+
+        let rollup = match timestep {
+            TimeseriesTimestep::FiveMinute => None,
+            TimeseriesTimestep::OneHour => Some((
+                "ge.price_1h",
+                sqlx::query!(
+                    "insert into ge.price_1h(bucket, item, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples) select to_timestamp(ts) at time zone 'utc', $1, ah, ah, ah, ah, al, al, al, al, 0 from unnest($2::bigint[], $3::bigint[], $4::bigint[]) as t(ts, ah, al) where ah is not null or al is not null on conflict (item, bucket) do nothing",
+                    item,
+                    &timestamps,
+                    &avg_high as &[Option<i64>],
+                    &avg_low as &[Option<i64>]
+                )
+                .execute(&mut *tx)
+                .await,
+            )),
+            TimeseriesTimestep::OneDay => Some((
+                "ge.price_1d",
+                sqlx::query!(
+                    "insert into ge.price_1d(bucket, item, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples) select to_timestamp(ts) at time zone 'utc', $1, ah, ah, ah, ah, al, al, al, al, 0 from unnest($2::bigint[], $3::bigint[], $4::bigint[]) as t(ts, ah, al) where ah is not null or al is not null on conflict (item, bucket) do nothing",
+                    item,
+                    &timestamps,
+                    &avg_high as &[Option<i64>],
+                    &avg_low as &[Option<i64>]
+                )
+                .execute(&mut *tx)
+                .await,
+            )),
+        };
+
+        if let Some((table, res)) = rollup {
+            match res {
+                Ok(e) => inserted += e.rows_affected(),
+                Err(e) => {
+                    println!("Error backfilling {} for {}: {:?}", table, item, e);
+                    return Err(failed(table));
+                }
+            };
+        }
+
+        let res = sqlx::query!(
+            "insert into ge.backfill(item, timestep, points) values ($1, $2, $3) on conflict (item, timestep) do update set points = excluded.points, finished = now() at time zone 'utc'",
+            item,
+            timestep.as_str(),
+            points.len() as i32
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error recording backfill of {}: {:?}", item, e);
+                return Err(failed("ge.backfill"));
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => Ok(inserted),
+            Err(e) => {
+                println!("Error committing backfill of {}: {:?}", item, e);
+                Err(failed("ge.backfill"))
+            }
+        }
+    }
+
+    /// Items already backfilled at a timestep, so a resumed run can skip them.
+    pub async fn get_backfilled(
+        &self,
+        timestep: &TimeseriesTimestep,
+    ) -> Result<Vec<i64>, DatabaseErrors> {
+        let res = sqlx::query_scalar!(
+            "select item from ge.backfill where timestep = $1",
+            timestep.as_str()
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error fetching backfill progress: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Forgets backfill progress for the given items at a timestep so they are fetched again.
+    pub async fn reset_backfill(
+        &self,
+        timestep: &TimeseriesTimestep,
+        items: &[i64],
+    ) -> Result<u64, DatabaseErrors> {
+        let res = sqlx::query!(
+            "delete from ge.backfill where timestep = $1 and item = any($2)",
+            timestep.as_str(),
+            items
+        )
+        .execute(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e.rows_affected()),
+            Err(e) => {
+                println!("Error resetting backfill progress: {:?}", e);
+                Err(DatabaseErrors::CannotDelete)
+            }
+        }
+    }
+
+    /// Upserts the wiki mapping into `ge.item`, first recording every changed name, members
+    /// flag, alch value or buy limit in `ge.item_history`. Returns the recorded changes.
+    pub async fn upsert_items(&self, maps: &[OsrsMap]) -> Result<Vec<ItemChange>, DatabaseErrors> {
+        if maps.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now().naive_utc();
+
+        let mut ids: Vec<i64> = Vec::with_capacity(maps.len());
+        let mut names: Vec<String> = Vec::with_capacity(maps.len());
+        let mut examines: Vec<String> = Vec::with_capacity(maps.len());
+        let mut members: Vec<bool> = Vec::with_capacity(maps.len());
+        let mut lowalch: Vec<Option<i64>> = Vec::with_capacity(maps.len());
+        let mut highalch: Vec<Option<i64>> = Vec::with_capacity(maps.len());
+        let mut limits: Vec<Option<i64>> = Vec::with_capacity(maps.len());
+        let mut values: Vec<i64> = Vec::with_capacity(maps.len());
+        let mut icons: Vec<String> = Vec::with_capacity(maps.len());
+
+        for d in maps {
+            ids.push(d.id);
+            names.push(d.name.clone());
+            examines.push(d.examine.clone());
+            members.push(d.members);
+            lowalch.push(d.lowalch);
+            highalch.push(d.highalch);
+            limits.push(d.limit);
+            values.push(d.value);
+            icons.push(d.icon.clone());
+        }
+
+        let failed = DatabaseErrors::CannotInsert {
+            table: "ge.item",
+            expected: maps.len(),
+            inserted: 0,
+        };
+
+        let mut tx = match self.database.begin().await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error starting item transaction: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let changes = sqlx::query_as!(
+            ItemChange,
+            r#"insert into ge.item_history(created, item, field, old_value, new_value)
+            select $1, n.id, c.field, c.old_value, c.new_value
+            from unnest($2::bigint[], $3::text[], $4::bool[], $5::bigint[], $6::bigint[], $7::bigint[])
+                as n(id, name, members, lowalch, highalch, buy_limit)
+            join ge.item i on i.id = n.id
+            cross join lateral (values
+                ('name', i.name, n.name),
+                ('members', i.members::text, n.members::text),
+                ('lowalch', i.lowalch::text, n.lowalch::text),
+                ('highalch', i.highalch::text, n.highalch::text),
+                ('limit', i.buy_limit::text, n.buy_limit::text)
+            ) as c(field, old_value, new_value)
+            where c.old_value is distinct from c.new_value
+            returning created, item, field, old_value, new_value"#,
+            &now,
+            &ids,
+            &names,
+            &members,
+            &lowalch as &[Option<i64>],
+            &highalch as &[Option<i64>],
+            &limits as &[Option<i64>]
+        )
+        .fetch_all(&mut *tx)
+        .await;
+
+        let changes = match changes {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error recording item changes: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let res = sqlx::query!(
+            "insert into ge.item(id, name, examine, members, lowalch, highalch, buy_limit, value, icon, updated)
+            select *, $10 from unnest($1::bigint[], $2::text[], $3::text[], $4::bool[], $5::bigint[], $6::bigint[], $7::bigint[], $8::bigint[], $9::text[])
+            on conflict (id) do update set
+                name = excluded.name, examine = excluded.examine, members = excluded.members,
+                lowalch = excluded.lowalch, highalch = excluded.highalch, buy_limit = excluded.buy_limit,
+                value = excluded.value, icon = excluded.icon, updated = excluded.updated
+            where (ge.item.name, ge.item.examine, ge.item.members, ge.item.lowalch, ge.item.highalch, ge.item.buy_limit, ge.item.value, ge.item.icon)
+                is distinct from (excluded.name, excluded.examine, excluded.members, excluded.lowalch, excluded.highalch, excluded.buy_limit, excluded.value, excluded.icon)",
+            &ids,
+            &names,
+            &examines,
+            &members,
+            &lowalch as &[Option<i64>],
+            &highalch as &[Option<i64>],
+            &limits as &[Option<i64>],
+            &values,
+            &icons,
+            &now
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error upserting items: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error committing items: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        return Ok(changes);
+    }
+
+    /// Every item stored in `ge.item`, used when the mapping endpoint is unavailable.
+    pub async fn get_items(&self) -> Result<Vec<OsrsMap>, DatabaseErrors> {
+        let res = sqlx::query_as!(
+            OsrsMap,
+            r#"select examine, id, members, lowalch, buy_limit as "limit", value, highalch, icon, name from ge.item"#
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error fetching items: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    pub async fn get_item_changes(&self, item: &i64) -> Result<Vec<ItemChange>, DatabaseErrors> {
+        let res = sqlx::query_as!(
+            ItemChange,
+            "select created, item, field, old_value, new_value from ge.item_history where item = $1 order by created desc",
+            item
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error fetching item changes for {}: {:?}", item, e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Every stored production recipe with its materials and skills in wiki order.
+    pub async fn get_recipes(&self) -> Result<Vec<CraftingItem>, DatabaseErrors> {
+        let recipes = sqlx::query!(
+            "select id, key, item, name, icon, ticks, facilities, members, output from production.recipe order by id"
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        let recipes = match recipes {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error fetching recipes: {:?}", e);
+                return Err(DatabaseErrors::CannotQuery);
+            }
+        };
+
+        let materials = sqlx::query!(
+            "select recipe, item, name, icon, count from production.recipe_material order by recipe, position"
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        let materials = match materials {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error fetching recipe materials: {:?}", e);
+                return Err(DatabaseErrors::CannotQuery);
+            }
+        };
+
+        let skills = sqlx::query!(
+            "select recipe, name, level, experience, boostable from production.recipe_skill order by recipe, position"
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        let skills = match skills {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error fetching recipe skills: {:?}", e);
+                return Err(DatabaseErrors::CannotQuery);
+            }
+        };
+
+        let mut recipe_materials: HashMap<i64, Vec<CraftingMaterial>> = HashMap::new();
+        for m in materials {
+            recipe_materials
+                .entry(m.recipe)
+                .or_default()
+                .push(CraftingMaterial {
+                    name: m.name,
+                    icon: m.icon,
+                    id: m.item,
+                    count: m.count as u8,
+                    limit: None,
+                });
+        }
+
+        let mut recipe_skills: HashMap<i64, Vec<CraftingSkill>> = HashMap::new();
+        for d in skills {
+            recipe_skills
+                .entry(d.recipe)
+                .or_default()
+                .push(CraftingSkill {
+                    experience: d.experience,
+                    level: d.level,
+                    name: d.name,
+                    boostable: d.boostable,
+                });
+        }
+
+        return Ok(recipes
+            .into_iter()
+            .map(|r| CraftingItem {
+                key: r.key,
+                name: r.name,
+                icon: r.icon,
+                id: r.item,
+                ticks: r.ticks,
+                materials: recipe_materials.remove(&r.id).unwrap_or_default(),
+                facilities: r.facilities,
+                skills: recipe_skills.remove(&r.id).unwrap_or_default(),
+                members: r.members,
+                output: r.output as u16,
+            })
+            .collect());
+    }
+
+    /// When the stored recipes were last compared against the wiki, if ever.
+    pub async fn get_recipes_checked(&self) -> Result<Option<NaiveDateTime>, DatabaseErrors> {
+        let res = sqlx::query_scalar!("select max(checked) from production.recipe")
+            .fetch_one(&self.database)
+            .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error fetching recipe check time: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Replaces the stored recipes with `recipes` in one transaction, rewriting only the ones
+    /// that were added or changed and logging every difference to `production.recipe_log`.
+    pub async fn save_recipes(
+        &self,
+        recipes: &[CraftingItem],
+    ) -> Result<Vec<RecipeChange>, DatabaseErrors> {
+        let stored = match self.get_recipes().await {
+            Ok(e) => e,
+            Err(e) => return Err(e),
+        };
+
+        let now = Utc::now().naive_utc();
+
+        let mut stored_by_key: HashMap<&str, &CraftingItem> =
+            stored.iter().map(|d| (d.key.as_str(), d)).collect();
+        let mut changes: Vec<RecipeChange> = Vec::new();
+        let mut write: Vec<&CraftingItem> = Vec::new();
+
+        for r in recipes {
+            match stored_by_key.remove(r.key.as_str()) {
+                Some(e) if e.same_recipe(r) => (),
+                Some(e) => {
+                    changes.push(RecipeChange {
+                        created: now,
+                        key: r.key.clone(),
+                        change: "changed".to_string(),
+                        old: serde_json::to_value(e).ok(),
+                        new: serde_json::to_value(r).ok(),
+                    });
+                    write.push(r);
+                }
+                None => {
+                    changes.push(RecipeChange {
+                        created: now,
+                        key: r.key.clone(),
+                        change: "added".to_string(),
+                        old: None,
+                        new: serde_json::to_value(r).ok(),
+                    });
+                    write.push(r);
+                }
+            }
+        }
+
+        // Whatever was not matched by the fresh recipes has been removed from the wiki
+        for (k, e) in stored_by_key {
+            changes.push(RecipeChange {
+                created: now,
+                key: k.to_string(),
+                change: "removed".to_string(),
+                old: serde_json::to_value(e).ok(),
+                new: None,
+            });
+        }
+
+        let failed = DatabaseErrors::CannotInsert {
+            table: "production.recipe",
+            expected: write.len(),
+            inserted: 0,
+        };
+
+        let mut tx = match self.database.begin().await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Error starting recipe transaction: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        // Changed recipes are deleted and inserted again along with the removed ones
+        let delete_keys: Vec<String> = changes
+            .iter()
+            .filter(|d| d.change != "added")
+            .map(|d| d.key.clone())
+            .collect();
+
+        let res = sqlx::query!(
+            "delete from production.recipe where key = any($1)",
+            &delete_keys
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error deleting recipes: {:?}", e);
+                return Err(DatabaseErrors::CannotDelete);
+            }
+        };
+
+        let res = sqlx::query!(
+            "insert into production.recipe(key, item, name, icon, ticks, facilities, members, output, created, checked)
+            select *, $9, $9 from unnest($1::text[], $2::bigint[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[], $8::int[])
+            returning id, key",
+            &write.iter().map(|d| d.key.clone()).collect::<Vec<String>>(),
+            &write.iter().map(|d| d.id).collect::<Vec<i64>>(),
+            &write.iter().map(|d| d.name.clone()).collect::<Vec<String>>(),
+            &write.iter().map(|d| d.icon.clone()).collect::<Vec<String>>(),
+            &write.iter().map(|d| d.ticks.clone()).collect::<Vec<String>>(),
+            &write.iter().map(|d| d.facilities.clone()).collect::<Vec<Option<String>>>() as &[Option<String>],
+            &write.iter().map(|d| d.members.clone()).collect::<Vec<String>>(),
+            &write.iter().map(|d| d.output as i32).collect::<Vec<i32>>(),
+            &now
+        )
+        .fetch_all(&mut *tx)
+        .await;
+
+        let ids: HashMap<String, i64> = match res {
+            Ok(e) => e.into_iter().map(|d| (d.key, d.id)).collect(),
+            Err(e) => {
+                println!("Error inserting recipes: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let mut m_recipe: Vec<i64> = Vec::new();
+        let mut m_position: Vec<i32> = Vec::new();
+        let mut m_item: Vec<i64> = Vec::new();
+        let mut m_name: Vec<String> = Vec::new();
+        let mut m_icon: Vec<String> = Vec::new();
+        let mut m_count: Vec<i32> = Vec::new();
+
+        let mut s_recipe: Vec<i64> = Vec::new();
+        let mut s_position: Vec<i32> = Vec::new();
+        let mut s_name: Vec<String> = Vec::new();
+        let mut s_level: Vec<String> = Vec::new();
+        let mut s_experience: Vec<String> = Vec::new();
+        let mut s_boostable: Vec<String> = Vec::new();
+
+        for r in &write {
+            let id = match ids.get(&r.key) {
+                Some(e) => *e,
+                None => continue,
             };
 
-            let low: Option<BigDecimal> = match d.low {
-                Some(e) => Some(BigDecimal::from(e)),
-                None => None,
+            for (i, m) in r.materials.iter().enumerate() {
+                m_recipe.push(id);
+                m_position.push(i as i32);
+                m_item.push(m.id);
+                m_name.push(m.name.clone());
+                m_icon.push(m.icon.clone());
+                m_count.push(m.count as i32);
+            }
+
+            for (i, d) in r.skills.iter().enumerate() {
+                s_recipe.push(id);
+                s_position.push(i as i32);
+                s_name.push(d.name.clone());
+                s_level.push(d.level.clone());
+                s_experience.push(d.experience.clone());
+                s_boostable.push(d.boostable.clone());
+            }
+        }
+
+        let res = sqlx::query!(
+            "insert into production.recipe_material(recipe, position, item, name, icon, count)
+            select * from unnest($1::bigint[], $2::int[], $3::bigint[], $4::text[], $5::text[], $6::int[])",
+            &m_recipe,
+            &m_position,
+            &m_item,
+            &m_name,
+            &m_icon,
+            &m_count
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error inserting recipe materials: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let res = sqlx::query!(
+            "insert into production.recipe_skill(recipe, position, name, level, experience, boostable)
+            select * from unnest($1::bigint[], $2::int[], $3::text[], $4::text[], $5::text[], $6::text[])",
+            &s_recipe,
+            &s_position,
+            &s_name,
+            &s_level,
+            &s_experience,
+            &s_boostable
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error inserting recipe skills: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let res = sqlx::query!(
+            "insert into production.recipe_log(created, key, change, old, new)
+            select $1, * from unnest($2::text[], $3::text[], $4::jsonb[], $5::jsonb[])",
+            &now,
+            &changes
+                .iter()
+                .map(|d| d.key.clone())
+                .collect::<Vec<String>>(),
+            &changes
+                .iter()
+                .map(|d| d.change.clone())
+                .collect::<Vec<String>>(),
+            &changes
+                .iter()
+                .map(|d| d.old.clone())
+                .collect::<Vec<Option<Value>>>() as &[Option<Value>],
+            &changes
+                .iter()
+                .map(|d| d.new.clone())
+                .collect::<Vec<Option<Value>>>() as &[Option<Value>]
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error logging recipe changes: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        let res = sqlx::query!("update production.recipe set checked = $1", &now)
+            .execute(&mut *tx)
+            .await;
+
+        match res {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error updating recipe check time: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error committing recipes: {:?}", e);
+                return Err(failed);
+            }
+        };
+
+        return Ok(changes);
+    }
+
+    /// The most recent recipe changes, newest first.
+    pub async fn get_recipe_changes(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<RecipeChange>, DatabaseErrors> {
+        let res = sqlx::query_as!(
+            RecipeChange,
+            "select created, key, change, old, new from production.recipe_log order by created desc limit $1",
+            limit
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error fetching recipe changes: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// The most recently stored row for every item, used to skip storing unchanged prices.
+    pub async fn get_latest_ge_prices(&self) -> Result<HashMap<i64, GePrice>, DatabaseErrors> {
+        let res = sqlx::query!(
+            r#"select distinct on (item) item, high::bigint as "high?", high_time, low::bigint as "low?", low_time from ge.price order by item, created desc"#
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e
+                .into_iter()
+                .map(|d| {
+                    (
+                        d.item,
+                        GePrice {
+                            high: d.high,
+                            high_time: d.high_time,
+                            low: d.low,
+                            low_time: d.low_time,
+                        },
+                    )
+                })
+                .collect()),
+            Err(e) => {
+                println!("Error fetching latest prices: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// The stored price of an item as it was at `at`, for comparing against the current one.
+    pub async fn get_ge_price_at(
+        &self,
+        item: &i64,
+        at: &NaiveDateTime,
+    ) -> Result<Option<GePrice>, DatabaseErrors> {
+        let res = sqlx::query!(
+            r#"select high::bigint as "high?", high_time, low::bigint as "low?", low_time from ge.price where item = $1 and created <= $2 order by created desc limit 1"#,
+            item,
+            at
+        )
+        .fetch_optional(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e.map(|d| GePrice {
+                high: d.high,
+                high_time: d.high_time,
+                low: d.low,
+                low_time: d.low_time,
+            })),
+            Err(e) => {
+                println!("Error fetching price of {} at {}: {:?}", item, at, e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    pub async fn get_alerts(&self) -> Result<Vec<AlertRule>, DatabaseErrors> {
+        let res = sqlx::query!(
+            "select id, item, kind, threshold, window_minutes, triggered, last_fired, created from ge.alert order by id"
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e
+                .into_iter()
+                .filter_map(|d| match AlertKind::parse(&d.kind) {
+                    Some(kind) => Some(AlertRule {
+                        id: d.id,
+                        item: d.item,
+                        kind,
+                        threshold: d.threshold,
+                        window_minutes: d.window_minutes,
+                        triggered: d.triggered,
+                        last_fired: d.last_fired,
+                        created: d.created,
+                    }),
+                    None => {
+                        println!("Skipping alert {} with unknown kind {}", d.id, d.kind);
+                        None
+                    }
+                })
+                .collect()),
+            Err(e) => {
+                println!("Error fetching alerts: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    pub async fn insert_alert(&self, rule: &NewAlertRule) -> Result<AlertRule, DatabaseErrors> {
+        let window = match rule.kind {
+            AlertKind::PercentChange => rule.window_minutes,
+            _ => None,
+        };
+
+        let res = sqlx::query!(
+            "insert into ge.alert(item, kind, threshold, window_minutes) values ($1, $2, $3, $4) returning id, triggered, last_fired, created",
+            rule.item,
+            rule.kind.as_str(),
+            rule.threshold,
+            window
+        )
+        .fetch_one(&self.database)
+        .await;
+
+        match res {
+            Ok(d) => Ok(AlertRule {
+                id: d.id,
+                item: rule.item,
+                kind: rule.kind,
+                threshold: rule.threshold,
+                window_minutes: window,
+                triggered: d.triggered,
+                last_fired: d.last_fired,
+                created: d.created,
+            }),
+            Err(e) => {
+                println!("Error inserting alert: {:?}", e);
+                Err(DatabaseErrors::CannotInsert {
+                    table: "ge.alert",
+                    expected: 1,
+                    inserted: 0,
+                })
+            }
+        }
+    }
+
+    /// Returns false if there was no alert with that id.
+    pub async fn delete_alert(&self, id: &i64) -> Result<bool, DatabaseErrors> {
+        let res = sqlx::query!("delete from ge.alert where id = $1", id)
+            .execute(&self.database)
+            .await;
+
+        match res {
+            Ok(e) => Ok(e.rows_affected() > 0),
+            Err(e) => {
+                println!("Error deleting alert {}: {:?}", id, e);
+                Err(DatabaseErrors::CannotDelete)
+            }
+        }
+    }
+
+    /// Records that an alert fired, or re-arms it once its condition no longer holds.
+    pub async fn set_alert_triggered(
+        &self,
+        id: &i64,
+        triggered: bool,
+    ) -> Result<(), DatabaseErrors> {
+        let res = sqlx::query!(
+            "update ge.alert set triggered = $2, last_fired = case when $2 then now() at time zone 'utc' else last_fired end where id = $1",
+            id,
+            triggered
+        )
+        .execute(&self.database)
+        .await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error updating alert {}: {:?}", id, e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Partitions of the partitioned `table`, oldest first.
+    pub async fn get_partitions(&self, table: &str) -> Result<Vec<String>, DatabaseErrors> {
+        let res = sqlx::query_scalar!(
+            r#"select inhrelid::regclass::text as "name!" from pg_inherits where inhparent = $1::text::regclass order by 1"#,
+            table
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error listing {} partitions: {:?}", table, e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Sets how long pg_partman keeps partitions of `table`, `None` keeping them forever.
+    /// Expired partitions are dropped by the next `run_partman_maintenance`.
+    pub async fn set_partman_retention(
+        &self,
+        table: &str,
+        days: Option<i64>,
+    ) -> Result<(), DatabaseErrors> {
+        // Not checked at compile time since the partman schema belongs to the extension
+        let res = sqlx::query(
+            "update partman.part_config set retention = $2, retention_keep_table = false, infinite_time_partitions = true where parent_table = $1",
+        )
+        .bind(table)
+        .bind(days.map(|d| format!("{} days", d)))
+        .execute(&self.database)
+        .await;
+
+        match res {
+            Ok(e) if e.rows_affected() == 0 => {
+                println!("{} is not managed by pg_partman", table);
+                Err(DatabaseErrors::CannotQuery)
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error setting retention for {}: {:?}", table, e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Creates upcoming partitions and drops expired ones for every partman managed table.
+    pub async fn run_partman_maintenance(&self) -> Result<(), DatabaseErrors> {
+        match sqlx::query("select partman.run_maintenance()")
+            .execute(&self.database)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error running partman maintenance: {:?}", e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    /// Deletes rows older than `cutoff` from the default partition of `table`, which partman
+    /// retention never drops. Returns the number of rows deleted.
+    pub async fn purge_default_partition(
+        &self,
+        table: &str,
+        control: &str,
+        cutoff: &NaiveDateTime,
+    ) -> Result<u64, DatabaseErrors> {
+        let default = format!("{}_default", table);
+
+        let exists = sqlx::query_scalar!(
+            r#"select to_regclass($1) is not null as "exists!""#,
+            &default
+        )
+        .fetch_one(&self.database)
+        .await;
+
+        match exists {
+            Ok(true) => (),
+            Ok(false) => return Ok(0),
+            Err(e) => {
+                println!("Error looking up {}: {:?}", default, e);
+                return Err(DatabaseErrors::CannotQuery);
+            }
+        };
+
+        // Table and column names come from `Retention`, never from user input
+        let res = sqlx::query(&format!("delete from {} where {} < $1", default, control))
+            .bind(cutoff)
+            .execute(&self.database)
+            .await;
+
+        match res {
+            Ok(e) => Ok(e.rows_affected()),
+            Err(e) => {
+                println!("Error purging {}: {:?}", default, e);
+                Err(DatabaseErrors::CannotDelete)
+            }
+        }
+    }
+
+    /// Deletes rows from one `ge.price` partition whose trade times match the previous row
+    /// stored for the same item, keeping the first row of every unchanged run. Returns the
+    /// number of rows deleted.
+    pub async fn dedupe_ge_price_partition(&self, partition: &str) -> Result<u64, DatabaseErrors> {
+        // Partition names come from pg_inherits and are already quoted by regclass. Each item's
+        // last row from before the partition is only compared against, never deleted, so a
+        // run that started in an earlier partition is still caught.
+        let res = sqlx::query(&format!(
+            "delete from {0} p using (
+                select item, created from (
+                    select item, created, high_time, low_time,
+                        lag(created) over w as prev_created,
+                        lag(high_time) over w as prev_high_time,
+                        lag(low_time) over w as prev_low_time
+                    from (
+                        select item, created, high_time, low_time from {0}
+                        union all
+                        (select distinct on (item) item, created, high_time, low_time
+                        from ge.price
+                        where created < (select min(created) from {0})
+                        order by item, created desc)
+                    ) r
+                    window w as (partition by item order by created)
+                ) t
+                where prev_created is not null
+                    and high_time is not distinct from prev_high_time
+                    and low_time is not distinct from prev_low_time
+            ) d
+            where p.item = d.item and p.created = d.created",
+            partition
+        ))
+        .execute(&self.database)
+        .await;
+
+        let deleted = match res {
+            Ok(e) => e.rows_affected(),
+            Err(e) => {
+                println!("Error deduplicating {}: {:?}", partition, e);
+                return Err(DatabaseErrors::CannotDelete);
+            }
+        };
+
+        // Make the freed space reusable straight away rather than waiting for autovacuum
+        match sqlx::query(&format!("vacuum (analyze) {}", partition))
+            .execute(&self.database)
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => println!("Error vacuuming {}: {:?}", partition, e),
+        };
+
+        Ok(deleted)
+    }
+
+    /// Rebuilds both rollups from one `ge.price` partition, overwriting any buckets already
+    /// there. Returns the number of hourly and daily buckets written.
+    pub async fn rebuild_ge_price_rollups(
+        &self,
+        partition: &str,
+    ) -> Result<(u64, u64), DatabaseErrors> {
+        let mut written: Vec<u64> = Vec::new();
+
+        for (table, unit) in [("ge.price_1h", "hour"), ("ge.price_1d", "day")] {
+            // Partitions are whole months, so no bucket spans two of them
+            let res = sqlx::query(&format!(
+                "insert into {0}(bucket, item, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples)
+                select date_trunc('{1}', created), item,
+                    (array_agg(high order by created) filter (where high is not null))[1]::bigint,
+                    max(high)::bigint,
+                    min(high)::bigint,
+                    (array_agg(high order by created desc) filter (where high is not null))[1]::bigint,
+                    (array_agg(low order by created) filter (where low is not null))[1]::bigint,
+                    max(low)::bigint,
+                    min(low)::bigint,
+                    (array_agg(low order by created desc) filter (where low is not null))[1]::bigint,
+                    count(*)
+                from {2}
+                group by 1, 2
+                on conflict (item, bucket) do update set
+                    high_open = excluded.high_open, high_high = excluded.high_high,
+                    high_low = excluded.high_low, high_close = excluded.high_close,
+                    low_open = excluded.low_open, low_high = excluded.low_high,
+                    low_low = excluded.low_low, low_close = excluded.low_close,
+                    samples = excluded.samples",
+                table, unit, partition
+            ))
+            .execute(&self.database)
+            .await;
+
+            match res {
+                Ok(e) => written.push(e.rows_affected()),
+                Err(e) => {
+                    println!("Error rebuilding {} from {}: {:?}", table, partition, e);
+                    return Err(DatabaseErrors::CannotInsert {
+                        table,
+                        expected: 0,
+                        inserted: 0,
+                    });
+                }
             };
-            sqlx::query!("insert into ge.price(item, high, high_time, low, low_time, created) values($1, $2, $3, $4, $5, $6)", &k, high, d.high_time, low, d.low_time, &now).execute(&self.database).await;
         }
-        return Ok(());
+
+        Ok((written[0], written[1]))
+    }
+
+    /// Price history for `item` since `since`, read from raw rows or one of the rollups
+    /// depending on how long the range is.
+    pub async fn get_ge_price_candles(
+        &self,
+        item: &i64,
+        since: Option<NaiveDateTime>,
+    ) -> Result<PriceHistory, DatabaseErrors> {
+        let resolution = Resolution::for_range(since);
+
+        let res = match resolution {
+            Resolution::Raw => {
+                return match self.get_ge_price_history(item, since).await {
+                    Ok(e) => Ok(PriceHistory {
+                        resolution,
+                        candles: e.into_iter().map(GePriceCandle::from).collect(),
+                    }),
+                    Err(e) => Err(e),
+                }
+            }
+            Resolution::Hour => {
+                sqlx::query_as!(
+                    GePriceCandle,
+                    r#"select bucket, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples::bigint as "samples!" from ge.price_1h where item = $1 and ($2::timestamp is null or bucket >= date_trunc('hour', $2)) order by bucket desc"#,
+                    item,
+                    since
+                )
+                .fetch_all(&self.database)
+                .await
+            }
+            Resolution::Day => {
+                sqlx::query_as!(
+                    GePriceCandle,
+                    r#"select bucket, high_open, high_high, high_low, high_close, low_open, low_high, low_low, low_close, samples::bigint as "samples!" from ge.price_1d where item = $1 and ($2::timestamp is null or bucket >= date_trunc('day', $2)) order by bucket desc"#,
+                    item,
+                    since
+                )
+                .fetch_all(&self.database)
+                .await
+            }
+        };
+
+        match res {
+            Ok(e) => Ok(PriceHistory {
+                resolution,
+                candles: e,
+            }),
+            Err(e) => {
+                println!(
+                    "Error fetching {} candles for {}: {:?}",
+                    resolution.as_str(),
+                    item,
+                    e
+                );
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
+    }
+
+    pub async fn get_ge_price_history(
+        &self,
+        item: &i64,
+        since: Option<NaiveDateTime>,
+    ) -> Result<Vec<GePriceHistory>, DatabaseErrors> {
+        let res = sqlx::query_as!(
+            GePriceHistory,
+            r#"select created, high::bigint as "high?", high_time, low::bigint as "low?", low_time from ge.price where item = $1 and ($2::timestamp is null or created >= $2) order by created desc"#,
+            item,
+            since
+        )
+        .fetch_all(&self.database)
+        .await;
+
+        match res {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                println!("Error fetching price history for {}: {:?}", item, e);
+                Err(DatabaseErrors::CannotQuery)
+            }
+        }
     }
 }
 
+#[derive(Debug)]
 pub enum DatabaseErrors {
-    CannotInsert,
+    CannotConnect,
+    CannotMigrate,
+    /// `inserted` of the `expected` rows were written before the batch was rolled back.
+    CannotInsert {
+        table: &'static str,
+        expected: usize,
+        inserted: u64,
+    },
+    CannotQuery,
+    CannotDelete,
+}
+
+impl fmt::Display for DatabaseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseErrors::CannotConnect => write!(f, "cannot connect to database"),
+            DatabaseErrors::CannotMigrate => write!(f, "cannot run migrations"),
+            DatabaseErrors::CannotInsert {
+                table,
+                expected,
+                inserted,
+            } => write!(
+                f,
+                "cannot insert into {} ({} of {} rows written, rolled back)",
+                table, inserted, expected
+            ),
+            DatabaseErrors::CannotQuery => write!(f, "cannot query"),
+            DatabaseErrors::CannotDelete => write!(f, "cannot delete"),
+        }
+    }
 }
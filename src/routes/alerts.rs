@@ -0,0 +1,88 @@
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use axum_valid::Valid;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use crate::repo::data::alert::{AlertKind, AlertRule, NewAlertRule, ALERT_KINDS};
+use crate::repo::data::error::OsrsError;
+use crate::routes::error::ErrorPage;
+use crate::routes::template::HtmlTemplate;
+use crate::AppState;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AlertsQuery {
+    pub item: Option<i64>,
+}
+
+pub async fn get(State(state): State<AppState>, Query(query): Query<AlertsQuery>) -> Response {
+    let rules = match state.database.get_alerts().await {
+        Ok(e) => e,
+        Err(e) => return ErrorPage::from(OsrsError::from(e)).into_response(),
+    };
+
+    let alerts = rules
+        .into_iter()
+        .map(|rule| AlertRow {
+            name: match state.osrs.get_map_one(&rule.item) {
+                Some(e) => e.name,
+                None => format!("Item {}", rule.item),
+            },
+            rule,
+        })
+        .collect();
+
+    let template = AlertsTemplate {
+        alerts,
+        kinds: ALERT_KINDS,
+        item: query.item,
+        time: time_opt,
+    };
+    HtmlTemplate(template).into_response()
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Valid(Form(rule)): Valid<Form<NewAlertRule>>,
+) -> Response {
+    if state.osrs.get_map_one(&rule.item).is_none() {
+        return ErrorPage::not_found(rule.item).into_response();
+    }
+
+    match state.database.insert_alert(&rule).await {
+        Ok(_) => Redirect::to("/alerts").into_response(),
+        Err(e) => ErrorPage::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+pub async fn delete(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    match state.database.delete_alert(&id).await {
+        Ok(_) => Redirect::to("/alerts").into_response(),
+        Err(e) => ErrorPage::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+struct AlertRow {
+    rule: AlertRule,
+    name: String,
+}
+
+#[derive(Template)]
+#[template(path = "alerts.html")]
+struct AlertsTemplate {
+    alerts: Vec<AlertRow>,
+    kinds: [AlertKind; 7],
+    item: Option<i64>,
+    time: fn(i: &Option<NaiveDateTime>) -> String,
+}
+
+fn time_opt(i: &Option<NaiveDateTime>) -> String {
+    match i {
+        Some(e) => e.format("%Y-%m-%d %H:%M UTC").to_string(),
+        None => "-".to_string(),
+    }
+}
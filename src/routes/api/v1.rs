@@ -0,0 +1,285 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_valid::Valid;
+use serde::Serialize;
+
+use crate::repo::data::alert::NewAlertRule;
+use crate::repo::data::chain::ChainProfit;
+use crate::repo::data::error::OsrsError;
+use crate::repo::data::osrs::{
+    CraftingItemProfit, FlipProfit, HighAlchProfit, LowAlchProfit, OsrsMap, VolumeTimestep,
+};
+use crate::repo::data::profile::Profile;
+use crate::repo::data::spell::SpellProfit;
+use crate::repo::data::table::TableQuery;
+use crate::routes::error::status_for;
+use crate::routes::items::HistoryQuery;
+use crate::AppState;
+
+pub async fn highalch(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Response {
+    match query.validate_sort::<HighAlchProfit>() {
+        Ok(_) => (),
+        Err(e) => return ApiError::bad_request(e).into_response(),
+    };
+
+    match state
+        .osrs
+        .get_high_alch_profit_priced(&query.pricing(), &query.rune_setup())
+    {
+        Ok(e) => Json(query.apply(e)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+pub async fn lowalch(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Response {
+    match query.validate_sort::<LowAlchProfit>() {
+        Ok(_) => (),
+        Err(e) => return ApiError::bad_request(e).into_response(),
+    };
+
+    match state
+        .osrs
+        .get_low_alch_profit_priced(&query.pricing(), &query.rune_setup())
+    {
+        Ok(e) => Json(query.apply(e)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+pub async fn crafting(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Response {
+    match query.validate_sort::<CraftingItemProfit>() {
+        Ok(_) => (),
+        Err(e) => return ApiError::bad_request(e).into_response(),
+    };
+
+    let profile = Profile::from_headers(&headers);
+
+    let mut profit = state.osrs.get_crafting_profit_priced(&query.pricing());
+    profit.retain(|d| profile.allows(d));
+
+    Json(query.apply(profit)).into_response()
+}
+
+pub async fn spells(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Response {
+    match query.validate_sort::<SpellProfit>() {
+        Ok(_) => (),
+        Err(e) => return ApiError::bad_request(e).into_response(),
+    };
+
+    Json(
+        query.apply(
+            state
+                .osrs
+                .get_spells_priced(&query.pricing(), &query.rune_setup()),
+        ),
+    )
+    .into_response()
+}
+
+pub async fn chains(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Response {
+    match query.validate_sort::<ChainProfit>() {
+        Ok(_) => (),
+        Err(e) => return ApiError::bad_request(e).into_response(),
+    };
+
+    Json(query.apply(state.osrs.get_chains())).into_response()
+}
+
+pub async fn chain(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    match state.osrs.get_chain(&id) {
+        Some(e) => Json(e).into_response(),
+        None => ApiError::not_found(id).into_response(),
+    }
+}
+
+pub async fn flipping(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Response {
+    match query.validate_sort::<FlipProfit>() {
+        Ok(_) => (),
+        Err(e) => return ApiError::bad_request(e).into_response(),
+    };
+
+    Json(query.apply(state.osrs.get_flipping())).into_response()
+}
+
+pub async fn status(State(state): State<AppState>) -> Response {
+    Json(state.osrs.get_freshness()).into_response()
+}
+
+/// The last 200 recipe additions, removals and changes picked up from the wiki.
+pub async fn recipe_changes(State(state): State<AppState>) -> Response {
+    match state.database.get_recipe_changes(200).await {
+        Ok(e) => Json(e).into_response(),
+        Err(e) => ApiError::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+pub async fn prices(State(state): State<AppState>) -> Response {
+    Json(state.osrs.get_ge_all()).into_response()
+}
+
+pub async fn price(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    match state.osrs.get_ge_one(&id) {
+        Some(e) => Json(e).into_response(),
+        None => ApiError::not_found(id).into_response(),
+    }
+}
+
+pub async fn volumes(
+    State(state): State<AppState>,
+    Path(timestep): Path<VolumeTimestep>,
+) -> Response {
+    Json(state.osrs.get_volume_all(&timestep)).into_response()
+}
+
+pub async fn volume(
+    State(state): State<AppState>,
+    Path((timestep, id)): Path<(VolumeTimestep, i64)>,
+) -> Response {
+    match state.osrs.get_volume_one(&timestep, &id) {
+        Some(e) => Json(e).into_response(),
+        None => ApiError::not_found(id).into_response(),
+    }
+}
+
+pub async fn mapping(State(state): State<AppState>) -> Response {
+    let mut maps: Vec<OsrsMap> = state.osrs.get_maps_all().into_values().collect();
+    maps.sort_by_key(|d| d.id);
+
+    Json(maps).into_response()
+}
+
+pub async fn mapping_one(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    match state.osrs.get_map_one(&id) {
+        Some(e) => Json(e).into_response(),
+        None => ApiError::not_found(id).into_response(),
+    }
+}
+
+pub async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    if state.osrs.get_map_one(&id).is_none() {
+        return ApiError::not_found(id).into_response();
+    }
+
+    match state
+        .database
+        .get_ge_price_candles(&id, query.window.since())
+        .await
+    {
+        Ok(e) => Json(e).into_response(),
+        Err(e) => ApiError::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+pub async fn changes(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    if state.osrs.get_map_one(&id).is_none() {
+        return ApiError::not_found(id).into_response();
+    }
+
+    match state.database.get_item_changes(&id).await {
+        Ok(e) => Json(e).into_response(),
+        Err(e) => ApiError::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+pub async fn alerts(State(state): State<AppState>) -> Response {
+    match state.database.get_alerts().await {
+        Ok(e) => Json(e).into_response(),
+        Err(e) => ApiError::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+pub async fn create_alert(
+    State(state): State<AppState>,
+    Valid(Json(rule)): Valid<Json<NewAlertRule>>,
+) -> Response {
+    if state.osrs.get_map_one(&rule.item).is_none() {
+        return ApiError::not_found(rule.item).into_response();
+    }
+
+    match state.database.insert_alert(&rule).await {
+        Ok(e) => (StatusCode::CREATED, Json(e)).into_response(),
+        Err(e) => ApiError::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+pub async fn delete_alert(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    match state.database.delete_alert(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => ApiError {
+            status: StatusCode::NOT_FOUND,
+            error: format!("No alert with id {}", id),
+        }
+        .into_response(),
+        Err(e) => ApiError::from(OsrsError::from(e)).into_response(),
+    }
+}
+
+/// Error body returned by every API route so clients only have to handle one shape.
+#[derive(Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub error: String,
+}
+
+impl ApiError {
+    pub fn not_found(id: i64) -> Self {
+        ApiError {
+            status: StatusCode::NOT_FOUND,
+            error: format!("No item with id {}", id),
+        }
+    }
+
+    pub fn bad_request(error: String) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            error,
+        }
+    }
+}
+
+impl From<OsrsError> for ApiError {
+    fn from(e: OsrsError) -> Self {
+        println!("{}", e);
+        ApiError {
+            status: status_for(&e),
+            error: match e {
+                OsrsError::Database(_) => "Failed to load data".to_string(),
+                _ => e.to_string(),
+            },
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
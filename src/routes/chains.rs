@@ -0,0 +1,85 @@
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+
+use axum_valid::Valid;
+
+use crate::repo::data::chain::{ChainProfit, ChainStep};
+use crate::repo::data::freshness::Freshness;
+use crate::repo::data::table::{Page, TableQuery};
+use crate::routes::error::ErrorPage;
+use crate::routes::format::{gp_opt, pretty_int, pretty_opt};
+use crate::routes::template::HtmlTemplate;
+use crate::AppState;
+
+pub async fn get(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Result<impl IntoResponse, ErrorPage> {
+    match query.validate_sort::<ChainProfit>() {
+        Ok(_) => (),
+        Err(e) => return Err(ErrorPage::bad_request(e)),
+    };
+
+    let page = query.apply(state.osrs.get_chains());
+    let template = IndexTemplate {
+        page,
+        query,
+        freshness: state.osrs.get_freshness(),
+        pretty: pretty_int,
+        pretty_opt,
+        gp_opt,
+        qty,
+    };
+    Ok(HtmlTemplate(template))
+}
+
+pub async fn get_one(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    let chain = match state.osrs.get_chain(&id) {
+        Some(e) => e,
+        None => return ErrorPage::not_found(id).into_response(),
+    };
+
+    let template = ChainTemplate {
+        steps: chain.tree.steps(),
+        chain,
+        freshness: state.osrs.get_freshness(),
+        pretty: pretty_int,
+        gp_opt,
+        qty,
+    };
+    HtmlTemplate(template).into_response()
+}
+
+#[derive(Template)]
+#[template(path = "chains.html")]
+struct IndexTemplate {
+    page: Page<ChainProfit>,
+    query: TableQuery,
+    freshness: Freshness,
+    pretty: fn(i: &i64) -> String,
+    pretty_opt: fn(i: &Option<i64>) -> String,
+    gp_opt: fn(i: &Option<i64>) -> String,
+    qty: fn(i: &f64) -> String,
+}
+
+#[derive(Template)]
+#[template(path = "chains/index.html")]
+struct ChainTemplate {
+    chain: ChainProfit,
+    steps: Vec<ChainStep>,
+    freshness: Freshness,
+    pretty: fn(i: &i64) -> String,
+    gp_opt: fn(i: &Option<i64>) -> String,
+    qty: fn(i: &f64) -> String,
+}
+
+/// Quantities and XP are fractional when a recipe makes several items at once.
+fn qty(i: &f64) -> String {
+    if i.fract().abs() < 0.005 {
+        return pretty_int(&(i.round() as i64));
+    }
+    format!("{:.2}", i)
+}
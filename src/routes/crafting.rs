@@ -1,44 +1,76 @@
 use askama::Template;
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
 };
 
+use std::sync::Arc;
+
+use axum_valid::Valid;
+
+use crate::repo::data::freshness::Freshness;
 use crate::repo::data::osrs::CraftingItemProfit;
+use crate::repo::data::profile::Profile;
+use crate::repo::data::table::{Page, TableQuery};
+use crate::repo::data::tax::GeTax;
+use crate::routes::error::ErrorPage;
+use crate::routes::format::{gp_opt, pretty_int, pretty_opt, rate};
 use crate::AppState;
 
-pub async fn get(State(state): State<AppState>) -> impl IntoResponse {
-    let crafting = state.osrs.get_crafting_profit();
+pub async fn get(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Result<impl IntoResponse, ErrorPage> {
+    match query.validate_sort::<CraftingItemProfit>() {
+        Ok(_) => (),
+        Err(e) => return Err(ErrorPage::bad_request(e)),
+    };
+
+    let profile = Profile::from_headers(&headers);
+
+    let mut profit = state.osrs.get_crafting_profit_priced(&query.pricing());
+    let total = profit.len();
+    profit.retain(|d| profile.allows(d));
+    let hidden = total - profit.len();
+
+    let page = query.apply(profit);
     let template = IndexTemplate {
-        crafting,
+        hidden,
+        page,
+        query,
+        skills: state.osrs.get_crafting_skills(),
+        profile,
+        freshness: state.osrs.get_freshness(),
+        tax: state.osrs.get_tax(),
         stringnull: stringnull,
         pretty: pretty_int,
+        pretty_opt,
+        gp_opt,
+        rate,
     };
-    HtmlTemplate(template)
+    Ok(HtmlTemplate(template))
 }
 
 #[derive(Template)]
 #[template(path = "crafting.html")]
 struct IndexTemplate {
-    crafting: Vec<CraftingItemProfit>,
+    page: Page<CraftingItemProfit>,
+    query: TableQuery,
+    skills: Vec<String>,
+    profile: Profile,
+    /// Recipes left out because the profile can't make them.
+    hidden: usize,
+    freshness: Freshness,
+    tax: Arc<GeTax>,
     pretty: fn(i: &i64) -> String,
+    pretty_opt: fn(i: &Option<i64>) -> String,
+    gp_opt: fn(i: &Option<i64>) -> String,
+    rate: fn(i: Option<f64>) -> String,
     stringnull: fn(i: &Option<String>) -> String,
 }
 
-fn pretty_int(i: &i64) -> String {
-    let mut s = String::new();
-    let i_str = i.to_string();
-    let a = i_str.chars().rev().enumerate();
-    for (idx, val) in a {
-        if idx != 0 && idx % 3 == 0 {
-            s.insert(0, ',');
-        }
-        s.insert(0, val);
-    }
-    s
-}
-
 fn stringnull(s: &Option<String>) -> String {
     match s {
         Some(e) => e.to_string(),
@@ -0,0 +1,71 @@
+use askama::Template;
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::repo::data::error::OsrsError;
+
+/// Rendered in place of a page when its data is not available.
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorPage {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ErrorPage {
+    pub fn not_found(id: i64) -> Self {
+        ErrorPage {
+            status: StatusCode::NOT_FOUND,
+            message: format!("No item with id {}", id),
+        }
+    }
+
+    pub fn bad_request(message: String) -> Self {
+        ErrorPage {
+            status: StatusCode::BAD_REQUEST,
+            message,
+        }
+    }
+}
+
+/// Upstream problems are temporary, so they are reported as 503 and the refresh loop will
+/// retry. Database failures are ours and stay a 500.
+pub fn status_for(e: &OsrsError) -> StatusCode {
+    match e {
+        OsrsError::Network(_) | OsrsError::Decode(_) | OsrsError::MissingReferencePrice(_) => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        OsrsError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl From<OsrsError> for ErrorPage {
+    fn from(e: OsrsError) -> Self {
+        println!("{}", e);
+        ErrorPage {
+            status: status_for(&e),
+            message: match e {
+                OsrsError::Database(_) => "Failed to load data, please try again later".to_string(),
+                OsrsError::MissingReferencePrice(_) => {
+                    "Prices are still being loaded, please try again in a few minutes".to_string()
+                }
+                _ => "The OSRS wiki is unavailable right now, please try again later".to_string(),
+            },
+        }
+    }
+}
+
+impl IntoResponse for ErrorPage {
+    fn into_response(self) -> Response {
+        match self.render() {
+            Ok(html) => (self.status, Html(html)).into_response(),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to render template. Error: {}", err),
+            )
+                .into_response(),
+        }
+    }
+}
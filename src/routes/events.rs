@@ -0,0 +1,29 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::AppState;
+
+/// Server-sent `refresh` events, one after every scheduled refresh, carrying the new
+/// freshness as JSON. The profit tables listen for these through the htmx SSE extension and
+/// swap in their rows again.
+pub async fn get(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.osrs.subscribe()).map(|update| {
+        // A client that fell behind has still missed a refresh, so it is told to reload anyway
+        let data = match update {
+            Ok(e) => serde_json::to_string(&e).unwrap_or_default(),
+            Err(_) => String::new(),
+        };
+
+        Ok(Event::default().event("refresh").data(data))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
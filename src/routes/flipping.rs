@@ -0,0 +1,52 @@
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+};
+
+use std::sync::Arc;
+
+use axum_valid::Valid;
+
+use crate::repo::data::freshness::Freshness;
+use crate::repo::data::osrs::FlipProfit;
+use crate::repo::data::table::{Page, TableQuery};
+use crate::repo::data::tax::GeTax;
+use crate::routes::error::ErrorPage;
+use crate::routes::format::{gp_opt, pretty_int, pretty_opt};
+use crate::routes::template::HtmlTemplate;
+use crate::AppState;
+
+pub async fn get(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Result<impl IntoResponse, ErrorPage> {
+    match query.validate_sort::<FlipProfit>() {
+        Ok(_) => (),
+        Err(e) => return Err(ErrorPage::bad_request(e)),
+    };
+
+    let page = query.apply(state.osrs.get_flipping());
+    let template = IndexTemplate {
+        page,
+        query,
+        freshness: state.osrs.get_freshness(),
+        tax: state.osrs.get_tax(),
+        pretty: pretty_int,
+        pretty_opt,
+        gp_opt,
+    };
+    Ok(HtmlTemplate(template))
+}
+
+#[derive(Template)]
+#[template(path = "flipping.html")]
+struct IndexTemplate {
+    page: Page<FlipProfit>,
+    query: TableQuery,
+    freshness: Freshness,
+    tax: Arc<GeTax>,
+    pretty: fn(i: &i64) -> String,
+    pretty_opt: fn(i: &Option<i64>) -> String,
+    gp_opt: fn(i: &Option<i64>) -> String,
+}
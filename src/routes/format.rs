@@ -0,0 +1,80 @@
+//! Number formatting shared by the page templates.
+
+/// Groups the digits in threes, keeping the sign out of the grouping so -123 stays "-123".
+pub fn pretty_int(i: &i64) -> String {
+    let mut s = String::new();
+    let i_str = i.unsigned_abs().to_string();
+    let a = i_str.chars().rev().enumerate();
+    for (idx, val) in a {
+        if idx != 0 && idx % 3 == 0 {
+            s.insert(0, ',');
+        }
+        s.insert(0, val);
+    }
+    if *i < 0 {
+        s.insert(0, '-');
+    }
+    s
+}
+
+pub fn pretty_opt(i: &Option<i64>) -> String {
+    match i {
+        Some(e) => pretty_int(e),
+        None => "-".to_string(),
+    }
+}
+
+pub fn gp_opt(i: &Option<i64>) -> String {
+    match i {
+        Some(e) => format!("{}gp", pretty_int(e)),
+        None => "-".to_string(),
+    }
+}
+
+/// Per-hour and per-XP rates, grouped once they are large and to two places below that.
+pub fn rate(i: Option<f64>) -> String {
+    match i {
+        Some(e) if e.abs() >= 1000_f64 => pretty_int(&(e.round() as i64)),
+        Some(e) => format!("{:.2}", e),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_int_groups_thousands() {
+        assert_eq!(pretty_int(&0), "0");
+        assert_eq!(pretty_int(&123), "123");
+        assert_eq!(pretty_int(&1234), "1,234");
+        assert_eq!(pretty_int(&1234567), "1,234,567");
+    }
+
+    #[test]
+    fn pretty_int_keeps_sign_out_of_grouping() {
+        assert_eq!(pretty_int(&-123), "-123");
+        assert_eq!(pretty_int(&-123456), "-123,456");
+        assert_eq!(pretty_int(&i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn pretty_opt_marks_missing_values() {
+        assert_eq!(pretty_opt(&Some(-2500)), "-2,500");
+        assert_eq!(pretty_opt(&None), "-");
+    }
+
+    #[test]
+    fn gp_opt_marks_missing_values() {
+        assert_eq!(gp_opt(&Some(1500)), "1,500gp");
+        assert_eq!(gp_opt(&None), "-");
+    }
+
+    #[test]
+    fn rate_rounds_large_values() {
+        assert_eq!(rate(Some(-12345.6)), "-12,346");
+        assert_eq!(rate(Some(1.234)), "1.23");
+        assert_eq!(rate(None), "-");
+    }
+}
@@ -1,51 +1,66 @@
 use askama::Template;
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
     response::{Html, IntoResponse, Response},
 };
 
+use axum_valid::Valid;
+
+use crate::repo::data::freshness::Freshness;
 use crate::repo::data::osrs::HighAlchProfit;
+use crate::repo::data::table::{Page, TableQuery};
+use crate::routes::error::ErrorPage;
+use crate::routes::format::{gp_opt, pretty_int, pretty_opt};
 use crate::AppState;
 
-pub async fn get(State(state): State<AppState>) -> impl IntoResponse {
-    let nr_price = match state.osrs.get_ge_one(&561_i64) {
-        Some(e) => match e.high {
-            Some(e) => e,
-            None => panic!("no nature ruin price"),
-        },
+pub async fn get(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Result<impl IntoResponse, ErrorPage> {
+    match query.validate_sort::<HighAlchProfit>() {
+        Ok(_) => (),
+        Err(e) => return Err(ErrorPage::bad_request(e)),
+    };
+
+    let pricing = query.pricing();
+
+    let setup = query.rune_setup();
+
+    let cast_cost = match state.osrs.get_cast_cost(&setup, true) {
+        Ok(e) => e,
+        Err(e) => return Err(ErrorPage::from(e)),
+    };
 
-        None => panic!("no nature ruin price"),
+    let profit = match state.osrs.get_high_alch_profit_priced(&pricing, &setup) {
+        Ok(e) => e,
+        Err(e) => return Err(ErrorPage::from(e)),
     };
 
-    let profits = state.osrs.get_high_alch_profit();
+    let page = query.apply(profit);
     let template = IndexTemplate {
-        profits,
-        nr_price,
+        page,
+        query,
+        freshness: state.osrs.get_freshness(),
+        cast_cost,
         pretty: pretty_int,
+        pretty_opt,
+        gp_opt,
     };
-    HtmlTemplate(template)
+    Ok(HtmlTemplate(template))
 }
 
 #[derive(Template)]
 #[template(path = "highalch.html")]
 struct IndexTemplate {
-    profits: Vec<HighAlchProfit>,
-    nr_price: i64,
+    page: Page<HighAlchProfit>,
+    query: TableQuery,
+    freshness: Freshness,
+    /// Rune cost of one cast with the chosen setup.
+    cast_cost: i64,
     pretty: fn(i: &i64) -> String,
-}
-
-fn pretty_int(i: &i64) -> String {
-    let mut s = String::new();
-    let i_str = i.to_string();
-    let a = i_str.chars().rev().enumerate();
-    for (idx, val) in a {
-        if idx != 0 && idx % 3 == 0 {
-            s.insert(0, ',');
-        }
-        s.insert(0, val);
-    }
-    s
+    pretty_opt: fn(i: &Option<i64>) -> String,
+    gp_opt: fn(i: &Option<i64>) -> String,
 }
 
 /// A wrapper type that we'll use to encapsulate HTML parsed by askama into valid HTML for axum to serve.
@@ -1,17 +1,25 @@
 use askama::Template;
 use axum::{
+    extract::State,
     http::StatusCode,
     response::{Html, IntoResponse, Response},
 };
 
-pub async fn get() -> impl IntoResponse {
-    let template = IndexTemplate {};
+use crate::repo::data::freshness::Freshness;
+use crate::AppState;
+
+pub async fn get(State(state): State<AppState>) -> impl IntoResponse {
+    let template = IndexTemplate {
+        freshness: state.osrs.get_freshness(),
+    };
     HtmlTemplate(template)
 }
 
 #[derive(Template)]
 #[template(path = "index.html")]
-struct IndexTemplate {}
+struct IndexTemplate {
+    freshness: Freshness,
+}
 
 /// A wrapper type that we'll use to encapsulate HTML parsed by askama into valid HTML for axum to serve.
 struct HtmlTemplate<T>(T);
@@ -0,0 +1,126 @@
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::Deserialize;
+
+use crate::repo::data::error::OsrsError;
+use crate::repo::data::freshness::Freshness;
+use crate::repo::data::osrs::{GePrice, ItemChange, OsrsMap, PriceHistory, Resolution};
+use crate::routes::error::ErrorPage;
+use crate::routes::format::gp_opt;
+use crate::routes::template::HtmlTemplate;
+use crate::AppState;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum HistoryWindow {
+    #[default]
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+    #[serde(rename = "all")]
+    All,
+}
+
+impl HistoryWindow {
+    pub fn since(&self) -> Option<NaiveDateTime> {
+        let now = Utc::now().naive_utc();
+        match self {
+            HistoryWindow::Day => Some(now - Duration::hours(24)),
+            HistoryWindow::Week => Some(now - Duration::days(7)),
+            HistoryWindow::Month => Some(now - Duration::days(30)),
+            HistoryWindow::All => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryWindow::Day => "24h",
+            HistoryWindow::Week => "7d",
+            HistoryWindow::Month => "30d",
+            HistoryWindow::All => "all",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    pub window: HistoryWindow,
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let item = match state.osrs.get_map_one(&id) {
+        Some(e) => e,
+        None => return ErrorPage::not_found(id).into_response(),
+    };
+
+    let price = state.osrs.get_ge_one(&id).unwrap_or_default();
+
+    let history = match state
+        .database
+        .get_ge_price_candles(&id, query.window.since())
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => return ErrorPage::from(OsrsError::from(e)).into_response(),
+    };
+
+    let changes = match state.database.get_item_changes(&id).await {
+        Ok(e) => e,
+        Err(e) => return ErrorPage::from(OsrsError::from(e)).into_response(),
+    };
+
+    let template = IndexTemplate {
+        item,
+        last_trade: price.last_trade(),
+        price,
+        history,
+        changes,
+        freshness: state.osrs.get_freshness(),
+        window: query.window,
+        windows: [
+            HistoryWindow::Day,
+            HistoryWindow::Week,
+            HistoryWindow::Month,
+            HistoryWindow::All,
+        ],
+        pretty: gp_opt,
+        time: time_opt,
+    };
+    HtmlTemplate(template).into_response()
+}
+
+#[derive(Template)]
+#[template(path = "items/index.html")]
+struct IndexTemplate {
+    item: OsrsMap,
+    price: GePrice,
+    last_trade: Option<i64>,
+    history: PriceHistory,
+    changes: Vec<ItemChange>,
+    freshness: Freshness,
+    window: HistoryWindow,
+    windows: [HistoryWindow; 4],
+    pretty: fn(i: &Option<i64>) -> String,
+    time: fn(i: &Option<i64>) -> String,
+}
+
+fn time_opt(i: &Option<i64>) -> String {
+    match i {
+        Some(e) => match DateTime::from_timestamp(*e, 0) {
+            Some(d) => d.format("%Y-%m-%d %H:%M UTC").to_string(),
+            None => "-".to_string(),
+        },
+        None => "-".to_string(),
+    }
+}
@@ -1,4 +1,15 @@
+pub mod alerts;
+pub mod api;
+pub mod chains;
 pub mod crafting;
+pub mod error;
+pub mod events;
+pub mod flipping;
+pub mod format;
 pub mod highalch;
 pub mod index;
+pub mod items;
 pub mod lowalch;
+pub mod profile;
+pub mod spells;
+pub mod template;
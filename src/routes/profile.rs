@@ -0,0 +1,51 @@
+use askama::Template;
+use axum::{
+    extract::State,
+    http::{header::SET_COOKIE, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+
+use crate::repo::data::profile::{Profile, MAX_BOOST, MAX_LEVEL};
+use crate::routes::error::ErrorPage;
+use crate::routes::template::HtmlTemplate;
+use crate::AppState;
+
+pub async fn get(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let template = ProfileTemplate {
+        skills: state.osrs.get_crafting_skills(),
+        profile: Profile::from_headers(&headers),
+        max_level: MAX_LEVEL,
+        max_boost: MAX_BOOST,
+    };
+    HtmlTemplate(template)
+}
+
+/// Saves the submitted levels to the profile cookie, or clears it when the form is empty.
+pub async fn save(Form(form): Form<Vec<(String, String)>>) -> Response {
+    let profile = match Profile::from_form(&form) {
+        Ok(e) => e,
+        Err(e) => {
+            return ErrorPage {
+                status: StatusCode::BAD_REQUEST,
+                message: e,
+            }
+            .into_response()
+        }
+    };
+
+    (
+        [(SET_COOKIE, profile.to_cookie())],
+        Redirect::to("/crafting"),
+    )
+        .into_response()
+}
+
+#[derive(Template)]
+#[template(path = "profile.html")]
+struct ProfileTemplate {
+    skills: Vec<String>,
+    profile: Profile,
+    max_level: u8,
+    max_boost: u8,
+}
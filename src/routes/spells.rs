@@ -0,0 +1,67 @@
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+};
+
+use std::sync::Arc;
+
+use axum_valid::Valid;
+
+use crate::repo::data::freshness::Freshness;
+use crate::repo::data::spell::SpellProfit;
+use crate::repo::data::table::{Page, TableQuery};
+use crate::repo::data::tax::GeTax;
+use crate::routes::error::ErrorPage;
+use crate::routes::format::{gp_opt, pretty_int, pretty_opt, rate};
+use crate::routes::template::HtmlTemplate;
+use crate::AppState;
+
+pub async fn get(
+    State(state): State<AppState>,
+    Valid(Query(query)): Valid<Query<TableQuery>>,
+) -> Result<impl IntoResponse, ErrorPage> {
+    match query.validate_sort::<SpellProfit>() {
+        Ok(_) => (),
+        Err(e) => return Err(ErrorPage::bad_request(e)),
+    };
+
+    let spells = state
+        .osrs
+        .get_spells_priced(&query.pricing(), &query.rune_setup());
+
+    let mut skills: Vec<String> = spells
+        .iter()
+        .flat_map(|d| d.skill_xp.iter().map(|s| s.skill.clone()))
+        .collect();
+    skills.sort();
+    skills.dedup();
+
+    let page = query.apply(spells);
+    let template = IndexTemplate {
+        page,
+        query,
+        skills,
+        freshness: state.osrs.get_freshness(),
+        tax: state.osrs.get_tax(),
+        pretty: pretty_int,
+        pretty_opt,
+        gp_opt,
+        rate,
+    };
+    Ok(HtmlTemplate(template))
+}
+
+#[derive(Template)]
+#[template(path = "spells.html")]
+struct IndexTemplate {
+    page: Page<SpellProfit>,
+    query: TableQuery,
+    skills: Vec<String>,
+    freshness: Freshness,
+    tax: Arc<GeTax>,
+    pretty: fn(i: &i64) -> String,
+    pretty_opt: fn(i: &Option<i64>) -> String,
+    gp_opt: fn(i: &Option<i64>) -> String,
+    rate: fn(i: Option<f64>) -> String,
+}